@@ -0,0 +1,639 @@
+//! Async driver implementing the `embedded-storage-async` NorFlash traits
+//!
+//! [`IS25xPAsync`] covers the array operations of [`IS25xP`](crate::IS25xP).
+//! While a program or erase is in progress the status register is read with
+//! a [`DelayNs`] in between, letting the executor run other tasks, unless the
+//! transport waits for the status itself through [`AsyncQspi::wait_status`].
+//! With a delay source given through [`IS25xPAsync::try_new_with_delay`] or
+//! [`IS25xPAsync::with_delay`], waits fail with [`Error::Timeout`] once an
+//! operation exceeds its maximum duration.
+
+use core::{
+    fmt::Debug,
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_storage::nor_flash::ErrorType;
+use embedded_storage_async::nor_flash::{MultiwriteNorFlash, NorFlash, ReadNorFlash};
+
+use crate::{
+    byte_range, check_protection, commands, flagged_error,
+    flash_params::SECTOR_SIZE,
+    qspi::{QspiMode, QspiReadCommand, QspiWriteCommand},
+    status::{ExtendedReadParameters, Status, QE, WIP},
+    AddressMode, Block, EraseRegion, Error, FlashParams, HalfBlock, IS25xPParams, JedecId,
+    MemoryMap, ProtectionArea, Sector, READ_BACK_CHUNK_SIZE,
+};
+
+/// Interval between status reads of the default [`AsyncQspi::wait_status`]
+const POLL_INTERVAL_US: u32 = 10;
+
+/// Async counterpart of [`Qspi`](crate::Qspi)
+#[allow(async_fn_in_trait)]
+pub trait AsyncQspi {
+    type Error: Debug;
+
+    async fn write(&mut self, cmd: QspiWriteCommand<'_>) -> Result<(), Self::Error>;
+    async fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Widest data phase supported by the transport. The driver falls back to
+    /// the single lane command set unless this is [`QspiMode::QuadChannel`].
+    fn lanes(&self) -> QspiMode {
+        QspiMode::QuadChannel
+    }
+
+    /// Read a register with `cmd` until the bits selected by `mask` equal
+    /// `value`, or until `timeout` microseconds have elapsed if given,
+    /// returning the last value read.
+    ///
+    /// The default implementation waits on `delay` between reads. Transports
+    /// able to poll the register in hardware, such as QUADSPI peripherals in
+    /// automatic polling mode, can wait for their status match interrupt
+    /// instead.
+    async fn wait_status<D: DelayNs>(
+        &mut self,
+        cmd: QspiReadCommand,
+        mask: u8,
+        value: u8,
+        timeout: Option<u32>,
+        delay: &mut D,
+    ) -> Result<u8, Self::Error> {
+        let mut elapsed = 0u32;
+        loop {
+            let mut reg = [0u8; 1];
+            self.transfer(cmd, &mut reg).await?;
+            if reg[0] & mask == value || timeout.is_some_and(|t| elapsed >= t) {
+                return Ok(reg[0]);
+            }
+            delay.delay_us(POLL_INTERVAL_US).await;
+            elapsed = elapsed.saturating_add(POLL_INTERVAL_US);
+        }
+    }
+}
+
+/// [`DelayNs`] yielding to the executor once instead of waiting, so that the
+/// status register is read as often as the executor allows
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Yield;
+
+impl DelayNs for Yield {
+    async fn delay_ns(&mut self, _ns: u32) {
+        YieldNow(false).await
+    }
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+pub struct IS25xPAsync<Q, P = IS25xPParams, D = Yield> {
+    qspi: Q,
+    id: JedecId,
+    params: P,
+    map: MemoryMap,
+    address_mode: AddressMode,
+    /// Area selected by the TBS bit, on devices with IS25LP/IS25WP block
+    /// protection
+    tbs: Option<ProtectionArea>,
+    delay: D,
+    /// Whether `delay` measures time, bounding the waits by the maximum
+    /// durations of the operations
+    timeouts: bool,
+    /// Read back programmed pages and erased regions
+    verify: bool,
+}
+
+impl<Q> IS25xPAsync<Q, IS25xPParams, Yield>
+where
+    Q: AsyncQspi,
+{
+    /// Initialize the driver, with the geometry selected from the density
+    /// reported by the attached device
+    pub async fn try_new(qspi: Q) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, IS25xPParams::default(), Yield, false).await?;
+        flash.detect().await?;
+        Ok(flash)
+    }
+}
+
+impl<Q, P> IS25xPAsync<Q, P, Yield>
+where
+    Q: AsyncQspi,
+    P: FlashParams,
+{
+    /// Initialize the driver for a device described by `params`, without
+    /// checking the identity of the attached device
+    pub async fn try_new_with_params(qspi: Q, params: P) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, params, Yield, false).await?;
+        flash.configure().await?;
+        Ok(flash)
+    }
+}
+
+impl<Q, D> IS25xPAsync<Q, IS25xPParams, D>
+where
+    Q: AsyncQspi,
+    D: DelayNs,
+{
+    /// Initialize the driver like [`IS25xPAsync::try_new`], waiting on `delay`
+    /// between status reads and failing with [`Error::Timeout`] once an
+    /// operation exceeds its maximum duration
+    pub async fn try_new_with_delay(qspi: Q, delay: D) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, IS25xPParams::default(), delay, true).await?;
+        flash.detect().await?;
+        Ok(flash)
+    }
+
+    /// Select the geometry from the density reported by the attached device
+    async fn detect(&mut self) -> Result<(), Error<Q::Error>> {
+        let params = IS25xPParams::from_jedec_id(&self.id).ok_or(Error::UnknownDevice(self.id))?;
+        self.map = MemoryMap::new(&params);
+        self.params = params;
+        self.configure().await
+    }
+}
+
+impl<Q, P, D> IS25xPAsync<Q, P, D>
+where
+    Q: AsyncQspi,
+    P: FlashParams,
+    D: DelayNs,
+{
+    async fn probe(qspi: Q, params: P, delay: D, timeouts: bool) -> Result<Self, Error<Q::Error>> {
+        let map = MemoryMap::new(&params);
+        let mut flash = IS25xPAsync {
+            qspi,
+            id: JedecId::from([0; 3]),
+            params,
+            map,
+            address_mode: AddressMode::ThreeByte,
+            tbs: None,
+            delay,
+            timeouts,
+            verify: false,
+        };
+        flash.wait_busy().await?;
+
+        let mut id = [0u8; 3];
+        flash
+            .qspi
+            .transfer(commands::READ_JEDEC_ID, &mut id)
+            .await
+            .map_err(Error::Qspi)?;
+        flash.id = id.into();
+        Ok(flash)
+    }
+
+    async fn configure(&mut self) -> Result<(), Error<Q::Error>> {
+        // The layout of the protection bits differs between manufacturers
+        self.tbs = if self.id.is_is25xp() {
+            let mut fr = [0u8; 1];
+            self.qspi
+                .transfer(commands::READ_FUNCTION, &mut fr)
+                .await
+                .map_err(Error::Qspi)?;
+            Some(crate::status::Function::from(fr[0]).tbs())
+        } else {
+            None
+        };
+
+        self.address_mode = AddressMode::select(&self.params, self.qspi.lanes());
+        if self.address_mode == AddressMode::FourByteMode {
+            let entry = self.params.four_byte_entry().ok_or(Error::Unsupported)?;
+            for &cmd in entry.commands() {
+                self.qspi.write(cmd).await.map_err(Error::Qspi)?;
+            }
+        }
+
+        // Set quad enable bit, preserving the remaining bits of the status
+        // register
+        if self.qspi.lanes() == QspiMode::QuadChannel && self.params.quad_enable() {
+            let status = self.status().await?;
+            if !status.qe() {
+                self.write_enable().await?;
+                self.qspi
+                    .write(
+                        commands::WRITE_STATUS.data(&[status.bits() | QE], QspiMode::SingleChannel),
+                    )
+                    .await
+                    .map_err(Error::Qspi)?;
+                self.wait_done(self.params.timeouts().register_write)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait on `delay` between status reads, failing with [`Error::Timeout`]
+    /// once an operation exceeds its maximum duration. Unlike [`Yield`],
+    /// `delay` has to actually wait for the timeouts to hold.
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> IS25xPAsync<Q, P, D2> {
+        IS25xPAsync {
+            qspi: self.qspi,
+            id: self.id,
+            params: self.params,
+            map: self.map,
+            address_mode: self.address_mode,
+            tbs: self.tbs,
+            delay,
+            timeouts: true,
+            verify: self.verify,
+        }
+    }
+
+    /// Geometry and command set in use
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    /// JEDEC ID read from the device during initialization
+    pub fn jedec_id(&self) -> JedecId {
+        self.id
+    }
+
+    /// Layout of the memory array of the attached device
+    pub fn memory_map(&self) -> MemoryMap {
+        self.map
+    }
+
+    /// Addressing used for the memory array, selected from its capacity
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    /// Whether programmed pages and erased regions are read back
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// Read back every page programmed by [`IS25xPAsync::write_page`] and
+    /// every region erased by the `erase_*` methods, failing with
+    /// [`Error::VerifyFailed`] at the first byte not holding the intended
+    /// value
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Release the transport
+    pub fn release(self) -> Q {
+        self.qspi
+    }
+
+    async fn status(&mut self) -> Result<Status, Error<Q::Error>> {
+        let mut sr_arr = [1u8; 1];
+        self.qspi
+            .transfer(commands::GET_STATUS, &mut sr_arr)
+            .await
+            .map_err(Error::Qspi)?;
+
+        Ok(sr_arr[0].into())
+    }
+
+    async fn write_enable(&mut self) -> Result<(), Error<Q::Error>> {
+        self.qspi
+            .write(commands::WRITE_ENABLE)
+            .await
+            .map_err(Error::Qspi)
+    }
+
+    /// Wait until no write is in progress, failing with [`Error::Timeout`]
+    /// after `timeout` microseconds if the delay source measures time
+    async fn wait_idle(&mut self, timeout: u32) -> Result<Status, Error<Q::Error>> {
+        let timeout = Some(timeout).filter(|_| self.timeouts);
+        let status: Status = self
+            .qspi
+            .wait_status(commands::GET_STATUS, WIP, 0, timeout, &mut self.delay)
+            .await
+            .map_err(Error::Qspi)?
+            .into();
+
+        if status.wip() {
+            return Err(Error::Timeout);
+        }
+        Ok(status)
+    }
+
+    /// Wait for any operation in progress, bounded by the longest operation
+    /// of the device
+    async fn wait_busy(&mut self) -> Result<Status, Error<Q::Error>> {
+        self.wait_idle(self.params.timeouts().chip_erase).await
+    }
+
+    /// Wait for a register write to complete within `timeout` microseconds.
+    /// A command dropped by the device leaves the write enable latch set, in
+    /// which case the latch is cleared and [`Error::WriteProtected`]
+    /// returned.
+    async fn wait_done(&mut self, timeout: u32) -> Result<(), Error<Q::Error>> {
+        let status = self.wait_idle(timeout).await?;
+        self.check_completed(&status).await
+    }
+
+    /// Wait for a program or erase to complete within `timeout`
+    /// microseconds, failing with the error flagged by the device, or with
+    /// [`Error::WriteProtected`] if the device dropped the command
+    async fn wait_operation(&mut self, timeout: u32) -> Result<(), Error<Q::Error>> {
+        let status = self.wait_idle(timeout).await?;
+        self.check_error_flags().await?;
+        self.check_completed(&status).await
+    }
+
+    /// Fail with [`Error::WriteProtected`] if the write enable latch is still
+    /// set after a command completed, clearing it
+    async fn check_completed(&mut self, status: &Status) -> Result<(), Error<Q::Error>> {
+        if status.wel() {
+            self.qspi
+                .write(commands::WRITE_DISABLE)
+                .await
+                .map_err(Error::Qspi)?;
+            return Err(Error::WriteProtected);
+        }
+
+        Ok(())
+    }
+
+    /// Read the extended read register (RDERP)
+    pub async fn extended_read_parameters(
+        &mut self,
+    ) -> Result<ExtendedReadParameters, Error<Q::Error>> {
+        let mut erp = [0u8; 1];
+        self.qspi
+            .transfer(commands::READ_EXTENDED_READ_PARAMETERS, &mut erp)
+            .await
+            .map_err(Error::Qspi)?;
+        Ok(erp[0].into())
+    }
+
+    /// Fail with the error flagged in the extended read register by the last
+    /// program or erase, clearing the flags (CLERP) so they do not fail the
+    /// next operation
+    async fn check_error_flags(&mut self) -> Result<(), Error<Q::Error>> {
+        // Only ISSI parts have the error flags
+        if !self.id.is_is25xp() {
+            return Ok(());
+        }
+
+        let erp = self.extended_read_parameters().await?;
+        let Some(error) = flagged_error(&erp) else {
+            return Ok(());
+        };
+
+        self.qspi
+            .write(commands::CLEAR_EXTENDED_READ_PARAMETERS)
+            .await
+            .map_err(Error::Qspi)?;
+        Err(error)
+    }
+
+    /// Fail with [`Error::WriteProtected`] if any address in `range` is
+    /// currently protected
+    async fn check_writable(&mut self, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        let status = self.status().await?;
+        check_protection(self.tbs, &self.map, &status, range)
+    }
+
+    pub async fn read_native(
+        &mut self,
+        offset: u32,
+        data: &mut [u8],
+    ) -> Result<(), Error<Q::Error>> {
+        byte_range::<Q::Error>(offset, data.len())?;
+        self.wait_busy().await?;
+        self.read_array(offset, data).await
+    }
+
+    async fn read_array(&mut self, offset: u32, data: &mut [u8]) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.read_command(self.qspi.lanes());
+        let cmd = self
+            .address_mode
+            .read_at(&self.params, cmd, offset)
+            .ok_or(Error::Unsupported)?;
+
+        self.qspi
+            .transfer(cmd.receive_length(data.len() as u32), data)
+            .await
+            .map_err(Error::Qspi)
+    }
+
+    /// Fail with [`Error::VerifyFailed`] if verification is enabled and any
+    /// byte of `range` is not erased
+    async fn check_erased(&mut self, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        if self.verify {
+            self.check_contents(range, |_| 0xFF).await?;
+        }
+        Ok(())
+    }
+
+    /// Read back `range`, failing with [`Error::VerifyFailed`] at the first
+    /// byte differing from `expected`, which is indexed from the start of
+    /// `range`
+    async fn check_contents(
+        &mut self,
+        range: Range<u32>,
+        expected: impl Fn(usize) -> u8,
+    ) -> Result<(), Error<Q::Error>> {
+        let mut buf = [0u8; READ_BACK_CHUNK_SIZE];
+        for address in range.clone().step_by(buf.len()) {
+            let len = (range.end - address).min(buf.len() as u32) as usize;
+            let chunk = &mut buf[..len];
+            self.read_array(address, chunk).await?;
+
+            let offset = (address - range.start) as usize;
+            if let Some(i) = (0..len).find(|&i| chunk[i] != expected(offset + i)) {
+                return Err(Error::VerifyFailed {
+                    address: address + i as u32,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn write_page(&mut self, offset: u32, data: &[u8]) -> Result<(), Error<Q::Error>> {
+        let status = self.status().await?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        if data.len() > self.params.page_size() as usize {
+            return Err(Error::Size);
+        }
+
+        let range = byte_range(offset, data.len())?;
+        check_protection(self.tbs, &self.map, &status, range.clone())?;
+
+        let cmd = self.params.program_command(self.qspi.lanes());
+        let data_mode = cmd.data.map_or(QspiMode::SingleChannel, |(_, m)| m);
+        let cmd = self
+            .address_mode
+            .write_at(&self.params, cmd, offset)
+            .ok_or(Error::Unsupported)?;
+        self.write_enable().await?;
+        self.qspi
+            .write(cmd.data(data, data_mode))
+            .await
+            .map_err(Error::Qspi)?;
+
+        self.wait_operation(self.params.timeouts().page_program)
+            .await?;
+
+        if self.verify {
+            self.check_contents(range, |i| data[i]).await?;
+        }
+        Ok(())
+    }
+
+    async fn erase_region(
+        &mut self,
+        cmd: QspiWriteCommand<'static>,
+        start: u32,
+        end: u32,
+        timeout: u32,
+    ) -> Result<(), Error<Q::Error>> {
+        let status = self.status().await?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        check_protection(self.tbs, &self.map, &status, start..end)?;
+
+        let cmd = self
+            .address_mode
+            .write_at(&self.params, cmd, start)
+            .ok_or(Error::Unsupported)?;
+        self.write_enable().await?;
+        self.qspi.write(cmd).await.map_err(Error::Qspi)?;
+        self.wait_operation(timeout).await?;
+        self.check_erased(start..end).await
+    }
+
+    pub async fn erase_sector(&mut self, sector: &Sector) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.sector_erase_command();
+        let timeout = self.params.timeouts().sector_erase;
+        self.erase_region(cmd, sector.start(), sector.end(), timeout)
+            .await
+    }
+
+    pub async fn erase_halfblock(&mut self, half_block: &HalfBlock) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.halfblock_erase_command();
+        let timeout = self.params.timeouts().halfblock_erase;
+        self.erase_region(cmd, half_block.start(), half_block.end(), timeout)
+            .await
+    }
+
+    pub async fn erase_block(&mut self, block: &Block) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.block_erase_command();
+        let timeout = self.params.timeouts().block_erase;
+        self.erase_region(cmd, block.start(), block.end(), timeout)
+            .await
+    }
+
+    pub async fn erase_chip(&mut self) -> Result<(), Error<Q::Error>> {
+        let status = self.status().await?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        // The device ignores a chip erase while any block is protected
+        check_protection(
+            self.tbs,
+            &self.map,
+            &status,
+            self.map.start()..self.map.end(),
+        )?;
+
+        self.write_enable().await?;
+        self.qspi
+            .write(commands::ERASE_CHIP)
+            .await
+            .map_err(Error::Qspi)?;
+        self.wait_operation(self.params.timeouts().chip_erase)
+            .await?;
+        self.check_erased(self.map.start()..self.map.end()).await
+    }
+}
+
+impl<Q: AsyncQspi, P: FlashParams, D: DelayNs> ErrorType for IS25xPAsync<Q, P, D> {
+    type Error = Error<Q::Error>;
+}
+
+impl<Q: AsyncQspi, P: FlashParams, D: DelayNs> ReadNorFlash for IS25xPAsync<Q, P, D> {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.map.checked_range::<Q::Error>(offset, bytes.len())?;
+        self.read_native(offset, bytes).await
+    }
+
+    fn capacity(&self) -> usize {
+        self.map.size()
+    }
+}
+
+impl<Q: AsyncQspi, P: FlashParams, D: DelayNs> NorFlash for IS25xPAsync<Q, P, D> {
+    const WRITE_SIZE: usize = 1;
+
+    /// Note: Devices described by a [`FlashParams`] with a sector size larger
+    /// than 4 KiB additionally require erases to be aligned to their sector
+    /// size.
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let range = self.map.checked_range(offset, bytes.len())?;
+        self.check_writable(range).await?;
+
+        for (address, page) in self.map.page_chunks(self.map.start() + offset, bytes) {
+            self.write_page(address, page).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn erase(&mut self, mut from: u32, to: u32) -> Result<(), Self::Error> {
+        self.map.check_erase_range(from, to)?;
+        self.check_writable(from..to).await?;
+
+        while from < to {
+            from = match self.map.erase_region_at(from, to)? {
+                EraseRegion::Chip => {
+                    self.erase_chip().await?;
+                    to
+                }
+                EraseRegion::Block(block) => {
+                    self.erase_block(&block).await?;
+                    block.end()
+                }
+                EraseRegion::HalfBlock(half_block) => {
+                    self.erase_halfblock(&half_block).await?;
+                    half_block.end()
+                }
+                EraseRegion::Sector(sector) => {
+                    self.erase_sector(&sector).await?;
+                    sector.end()
+                }
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Note: A program operation can alter “1”s into “0”s. The same byte location
+/// or page may be programmed more than once, to incrementally change “1”s to
+/// “0”s. An erase operation is required to change “0”s to “1”s.
+impl<Q: AsyncQspi, P: FlashParams, D: DelayNs> MultiwriteNorFlash for IS25xPAsync<Q, P, D> {}
@@ -0,0 +1,166 @@
+//! Checksums computed over regions of the memory array
+//!
+//! [`IS25xP::checksum`](crate::IS25xP::checksum) streams a region through
+//! any [`Checksum`], so a hardware CRC unit can be used in place of the
+//! software [`Crc32`] and [`Crc16`] by implementing the trait for it.
+
+/// Checksum computed incrementally over a stream of bytes
+pub trait Checksum {
+    type Output;
+
+    /// Feed the next `data` bytes of the stream
+    fn update(&mut self, data: &[u8]);
+
+    /// Checksum of the bytes fed so far
+    fn finish(&self) -> Self::Output;
+}
+
+/// CRC-32 as used by Ethernet, zlib and PNG (CRC-32/ISO-HDLC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+const CRC32_POLY: u32 = 0xEDB8_8320;
+const CRC32_TABLE: [u32; 16] = crc32_table();
+
+/// Table of the reflected CRC-32 of every nibble
+const fn crc32_table() -> [u32; 16] {
+    let mut table = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 4 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self { crc: !0 }
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checksum for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let crc = self.crc ^ byte as u32;
+            let crc = (crc >> 4) ^ CRC32_TABLE[(crc & 0xF) as usize];
+            self.crc = (crc >> 4) ^ CRC32_TABLE[(crc & 0xF) as usize];
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+/// CRC-16 with polynomial 0x1021 and initial value 0xFFFF, also known as
+/// CRC-16/CCITT-FALSE (CRC-16/IBM-3740)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc16 {
+    crc: u16,
+}
+
+const CRC16_POLY: u16 = 0x1021;
+const CRC16_TABLE: [u16; 16] = crc16_table();
+
+/// Table of the CRC-16 of every nibble, shifted to the top of the register
+const fn crc16_table() -> [u16; 16] {
+    let mut table = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        let mut crc = (i as u16) << 12;
+        let mut bit = 0;
+        while bit < 4 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ CRC16_POLY
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+impl Crc16 {
+    pub const fn new() -> Self {
+        Self { crc: 0xFFFF }
+    }
+}
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checksum for Crc16 {
+    type Output = u16;
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let crc = self.crc ^ ((byte as u16) << 8);
+            let crc = (crc << 4) ^ CRC16_TABLE[(crc >> 12) as usize];
+            self.crc = (crc << 4) ^ CRC16_TABLE[(crc >> 12) as usize];
+        }
+    }
+
+    fn finish(&self) -> u16 {
+        self.crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn crc32() {
+        let mut crc = Crc32::new();
+        crc.update(CHECK);
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+
+        let mut crc = Crc32::new();
+        crc.update(&CHECK[..4]);
+        crc.update(&CHECK[4..]);
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+
+    #[test]
+    fn crc16() {
+        let mut crc = Crc16::new();
+        crc.update(CHECK);
+        assert_eq!(crc.finish(), 0x29B1);
+
+        let mut crc = Crc16::new();
+        crc.update(&CHECK[..5]);
+        crc.update(&CHECK[5..]);
+        assert_eq!(crc.finish(), 0x29B1);
+    }
+}
@@ -1,9 +1,36 @@
-use stm32l4xx_hal::qspi::{QspiMode, QspiReadCommand, QspiWriteCommand};
+use crate::qspi::{AddressSize, QspiMode, QspiReadCommand, QspiWriteCommand};
 
 pub const QPI_ENABLE: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x35, QspiMode::SingleChannel)),
     address: None,
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const QPI_DISABLE: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0xF5, QspiMode::QuadChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const ENTER_4BYTE_ADDRESS: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0xB7, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const EXIT_4BYTE_ADDRESS: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x29, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -12,7 +39,7 @@ pub const QPI_ENABLE: QspiWriteCommand = QspiWriteCommand {
 pub const WRITE_STATUS: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x01, QspiMode::SingleChannel)),
     address: None,
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -21,7 +48,34 @@ pub const WRITE_STATUS: QspiWriteCommand = QspiWriteCommand {
 pub const WRITE_ENABLE: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x06, QspiMode::SingleChannel)),
     address: None,
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const WRITE_DISABLE: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x04, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const SUSPEND: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x75, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const RESUME: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x7A, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -30,17 +84,161 @@ pub const WRITE_ENABLE: QspiWriteCommand = QspiWriteCommand {
 pub const GET_STATUS: QspiReadCommand = QspiReadCommand {
     instruction: Some((0x05, QspiMode::SingleChannel)),
     address: None,
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data_mode: QspiMode::SingleChannel,
     receive_length: 1,
     double_data_rate: false,
 };
 
+pub const READ_FUNCTION: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x48, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 1,
+    double_data_rate: false,
+};
+
+pub const WRITE_FUNCTION: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x42, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const READ_READ_PARAMETERS: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x61, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 1,
+    double_data_rate: false,
+};
+
+pub const SET_READ_PARAMETERS: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0xC0, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const READ_EXTENDED_READ_PARAMETERS: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x81, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 1,
+    double_data_rate: false,
+};
+
+pub const SET_EXTENDED_READ_PARAMETERS: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x83, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const CLEAR_EXTENDED_READ_PARAMETERS: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x82, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const READ_JEDEC_ID: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x9F, QspiMode::SingleChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 3,
+    double_data_rate: false,
+};
+
+pub const READ_JEDEC_ID_QPI: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0xAF, QspiMode::QuadChannel)),
+    address: None,
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::QuadChannel,
+    receive_length: 3,
+    double_data_rate: false,
+};
+
+pub const READ_MANUFACTURER_ID: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x90, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 2,
+    double_data_rate: false,
+};
+
+pub const READ_UNIQUE_ID: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x4B, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 16,
+    double_data_rate: false,
+};
+
+pub const READ_SFDP: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x5A, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
+pub const READ_INFORMATION_ROW: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x68, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
+pub const PROGRAM_INFORMATION_ROW: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x62, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
+pub const ERASE_INFORMATION_ROW: QspiWriteCommand = QspiWriteCommand {
+    instruction: Some((0x64, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data: None,
+    double_data_rate: false,
+};
+
 pub const WRITE: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x02, QspiMode::SingleChannel)),
     address: Some((0x8, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -49,26 +247,96 @@ pub const WRITE: QspiWriteCommand = QspiWriteCommand {
 pub const QUAD_WRITE: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x32, QspiMode::SingleChannel)),
     address: Some((0x8, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: Some((&[0], QspiMode::QuadChannel)),
     double_data_rate: false,
 };
 
+pub const NORMAL_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x03, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 0,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
 pub const READ: QspiReadCommand = QspiReadCommand {
     instruction: Some((0x0b, QspiMode::SingleChannel)),
     address: Some((0x6, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 8,
     data_mode: QspiMode::SingleChannel,
     receive_length: 0,
     double_data_rate: false,
 };
 
+pub const DUAL_OUTPUT_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x3B, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::DualChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
+pub const DUAL_IO_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0xBB, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::DualChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 4,
+    data_mode: QspiMode::DualChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
+pub const QUAD_OUTPUT_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x6B, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::QuadChannel,
+    receive_length: 0,
+    double_data_rate: false,
+};
+
+pub const DTR_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0x0D, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::SingleChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 8,
+    data_mode: QspiMode::SingleChannel,
+    receive_length: 0,
+    double_data_rate: true,
+};
+
+pub const DUAL_IO_DTR_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0xBD, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::DualChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 4,
+    data_mode: QspiMode::DualChannel,
+    receive_length: 0,
+    double_data_rate: true,
+};
+
+pub const QUAD_IO_DTR_READ: QspiReadCommand = QspiReadCommand {
+    instruction: Some((0xED, QspiMode::SingleChannel)),
+    address: Some((0x0, QspiMode::QuadChannel)),
+    address_size: AddressSize::Addr24Bit,
+    dummy_cycles: 6,
+    data_mode: QspiMode::QuadChannel,
+    receive_length: 0,
+    double_data_rate: true,
+};
+
 pub const QUAD_READ: QspiReadCommand = QspiReadCommand {
     instruction: Some((0xEB, QspiMode::SingleChannel)),
     address: Some((0x0, QspiMode::QuadChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 6,
     data_mode: QspiMode::QuadChannel,
     receive_length: 0,
@@ -78,7 +346,7 @@ pub const QUAD_READ: QspiReadCommand = QspiReadCommand {
 pub const ERASE_CHIP: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0xC7, QspiMode::SingleChannel)),
     address: None,
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -87,7 +355,7 @@ pub const ERASE_CHIP: QspiWriteCommand = QspiWriteCommand {
 pub const ERASE_BLOCK: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0xD8, QspiMode::SingleChannel)),
     address: Some((0, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -96,7 +364,7 @@ pub const ERASE_BLOCK: QspiWriteCommand = QspiWriteCommand {
 pub const ERASE_HALF_BLOCK: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0x52, QspiMode::SingleChannel)),
     address: Some((0, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
@@ -105,8 +373,30 @@ pub const ERASE_HALF_BLOCK: QspiWriteCommand = QspiWriteCommand {
 pub const ERASE_SECTOR: QspiWriteCommand = QspiWriteCommand {
     instruction: Some((0xD7, QspiMode::SingleChannel)),
     address: Some((0, QspiMode::SingleChannel)),
-    alternative_bytes: None,
+    address_size: AddressSize::Addr24Bit,
     dummy_cycles: 0,
     data: None,
     double_data_rate: false,
 };
+
+/// Counterpart of a read, program or erase opcode taking a 4 byte address on
+/// the IS25LP/IS25WP parts larger than 16 MiB
+pub const fn four_byte_opcode(opcode: u8) -> Option<u8> {
+    match opcode {
+        0x03 => Some(0x13),
+        0x0B => Some(0x0C),
+        0x3B => Some(0x3C),
+        0xBB => Some(0xBC),
+        0x6B => Some(0x6C),
+        0xEB => Some(0xEC),
+        0x0D => Some(0x0E),
+        0xBD => Some(0xBE),
+        0xED => Some(0xEE),
+        0x02 => Some(0x12),
+        0x32 | 0x38 => Some(0x34),
+        0x20 | 0xD7 => Some(0x21),
+        0x52 => Some(0x5C),
+        0xD8 => Some(0xDC),
+        _ => None,
+    }
+}
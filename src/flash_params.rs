@@ -1,174 +1,344 @@
-use embedded_storage::Region;
-
+use crate::{
+    commands,
+    id::{Density, JedecId},
+    qspi::{AddressSize, QspiMode, QspiReadCommand, QspiWriteCommand},
+    sfdp::{BasicFlashParameters, EraseType, FastRead, QuadEnable},
+};
 
+/// Geometry and command set of the attached flash
 pub trait FlashParams {
-    
-}
+    /// Size of a program page in bytes
+    fn page_size(&self) -> u32;
 
-pub struct JedecAuto;
+    /// Size of the smallest erasable unit in bytes
+    fn sector_size(&self) -> u32;
 
-impl FlashParams for JedecAuto {
-    
-}
+    /// Size of the intermediate erasable unit in bytes
+    fn halfblock_size(&self) -> u32;
 
-// ------------------------------------
+    /// Size of the largest erasable unit in bytes
+    fn block_size(&self) -> u32;
 
+    /// Size of the memory array in bytes
+    fn capacity(&self) -> u32;
 
-pub struct MemoryMap;
-pub struct Block(u32);
-pub struct HalfBlock(u32);
-pub struct Sector(u32);
-pub struct Page(u32);
+    /// Command erasing the sector at the address supplied by the driver
+    fn sector_erase_command(&self) -> QspiWriteCommand<'static>;
 
-const BASE_ADDRESS: u32 = 0x0000_0000;
-const PAGES_PER_SECTOR: u32 = 16;
-const SECTORS_PER_BLOCK: u32 = 16;
-const SECTORS_PER_HALFBLOCK: u32 = 8;
-const HALFBLOCKS_PER_BLOCK: u32 = SECTORS_PER_BLOCK / SECTORS_PER_HALFBLOCK;
-const PAGES_PER_BLOCK: u32 = PAGES_PER_SECTOR * SECTORS_PER_BLOCK;
-const PAGES_PER_HALFBLOCK: u32 = PAGES_PER_SECTOR * HALFBLOCKS_PER_BLOCK;
-const PAGE_SIZE: u32 = 256;
-const SECTOR_SIZE: u32 = PAGE_SIZE * PAGES_PER_SECTOR;
-const HALFBLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_HALFBLOCK;
-const BLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_BLOCK;
-const MEMORY_SIZE: u32 = NUMBER_OF_BLOCKS * BLOCK_SIZE;
-const NUMBER_OF_BLOCKS: u32 = 256;
-const NUMBER_OF_HALFBLOCKS: u32 = NUMBER_OF_BLOCKS * HALFBLOCKS_PER_BLOCK;
-const NUMBER_OF_SECTORS: u32 = NUMBER_OF_BLOCKS * SECTORS_PER_BLOCK;
-const NUMBER_OF_PAGES: u32 = NUMBER_OF_SECTORS * PAGES_PER_SECTOR;
+    /// Command erasing the half block at the address supplied by the driver
+    fn halfblock_erase_command(&self) -> QspiWriteCommand<'static>;
 
-impl MemoryMap {
-    pub fn blocks() -> impl Iterator<Item = Block> {
-        (0..NUMBER_OF_BLOCKS).map(Block)
-    }
-    pub fn halfblocks() -> impl Iterator<Item = HalfBlock> {
-        (0..NUMBER_OF_HALFBLOCKS).map(HalfBlock)
+    /// Command erasing the block at the address supplied by the driver
+    fn block_erase_command(&self) -> QspiWriteCommand<'static>;
+
+    /// Read command to use on a transport with `lanes` data lanes. The
+    /// returned command carries the instruction, the lane configuration and
+    /// the number of dummy cycles, and is completed with an address and a
+    /// length by the driver.
+    fn read_command(&self, lanes: QspiMode) -> QspiReadCommand;
+
+    /// Page program command to use on a transport with `lanes` data lanes
+    fn program_command(&self, lanes: QspiMode) -> QspiWriteCommand<'static>;
+
+    /// Whether the Quad Enable bit (bit 6 of the status register) has to be
+    /// set before issuing quad commands
+    fn quad_enable(&self) -> bool {
+        true
     }
-    pub fn sectors() -> impl Iterator<Item = Sector> {
-        (0..NUMBER_OF_SECTORS).map(Sector)
+
+    /// Counterpart of the read, program or erase `opcode` taking a 4 byte
+    /// address regardless of the address mode of the device. Devices larger
+    /// than 16 MiB without such opcodes are switched to 4 byte address mode
+    /// instead.
+    fn four_byte_opcode(&self, _opcode: u8) -> Option<u8> {
+        None
     }
-    pub fn pages() -> impl Iterator<Item = Page> {
-        (0..NUMBER_OF_PAGES).map(Page)
+
+    /// Maximum durations of the program, erase and register write operations
+    fn timeouts(&self) -> Timeouts {
+        Timeouts::for_capacity(self.capacity())
     }
-    pub const fn start() -> u32 {
-        BASE_ADDRESS
+
+    /// How the device is switched to 4 byte addresses when larger than
+    /// 16 MiB without 4 byte opcodes, or `None` if it cannot be
+    fn four_byte_entry(&self) -> Option<FourByteEntry> {
+        Some(FourByteEntry::Instruction)
     }
-    pub const fn end() -> u32 {
-        BASE_ADDRESS + MEMORY_SIZE as u32
+}
+
+/// Method switching a device to 4 byte address mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FourByteEntry {
+    /// Enter 4 byte address mode (EN4B, 0xB7)
+    Instruction,
+    /// Enter 4 byte address mode (EN4B, 0xB7), preceded by a write enable
+    WriteEnableInstruction,
+    /// The device only takes 4 byte addresses
+    Always,
+}
+
+impl FourByteEntry {
+    /// Commands to send after reset for the device to take 4 byte addresses
+    pub(crate) fn commands(self) -> &'static [QspiWriteCommand<'static>] {
+        match self {
+            FourByteEntry::Instruction => &[commands::ENTER_4BYTE_ADDRESS],
+            FourByteEntry::WriteEnableInstruction => {
+                &[commands::WRITE_ENABLE, commands::ENTER_4BYTE_ADDRESS]
+            }
+            FourByteEntry::Always => &[],
+        }
     }
-    pub const fn size() -> usize {
-        MEMORY_SIZE as usize
+}
+
+/// Maximum durations of the operations of a device, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub page_program: u32,
+    pub sector_erase: u32,
+    pub halfblock_erase: u32,
+    pub block_erase: u32,
+    pub chip_erase: u32,
+    /// Write of the status or function register
+    pub register_write: u32,
+}
+
+impl Timeouts {
+    /// Maximum durations from the IS25LP128/IS25WP128 datasheet, with the chip
+    /// erase time scaled to a memory array of `capacity` bytes
+    pub fn for_capacity(capacity: u32) -> Self {
+        let chip_erase = 180_000_000 * capacity as u64 / (16 * 1024 * 1024);
+
+        Self {
+            page_program: 800,
+            sector_erase: 300_000,
+            halfblock_erase: 500_000,
+            block_erase: 1_000_000,
+            chip_erase: chip_erase.min(u32::MAX as u64) as u32,
+            register_write: 15_000,
+        }
     }
 }
 
-impl Block {
-    pub fn sectors(&self) -> impl Iterator<Item = Sector> {
-        ((self.0 * SECTORS_PER_BLOCK)..((1 + self.0) * SECTORS_PER_BLOCK)).map(Sector)
+pub(crate) const PAGES_PER_SECTOR: u32 = 16;
+pub(crate) const SECTORS_PER_BLOCK: u32 = 16;
+pub(crate) const SECTORS_PER_HALFBLOCK: u32 = 8;
+pub(crate) const PAGE_SIZE: u32 = 256;
+pub(crate) const SECTOR_SIZE: u32 = PAGE_SIZE * PAGES_PER_SECTOR;
+pub(crate) const HALFBLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_HALFBLOCK;
+pub(crate) const BLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_BLOCK;
+
+/// Parameters of the ISSI IS25LP/IS25WP family, which only differ in density
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IS25xPParams {
+    density: Density,
+}
+
+impl IS25xPParams {
+    pub const fn new(density: Density) -> Self {
+        Self { density }
     }
-    pub fn halfblocks(&self) -> impl Iterator<Item = HalfBlock> {
-        ((self.0 * HALFBLOCKS_PER_BLOCK)..((1 + self.0) * HALFBLOCKS_PER_BLOCK)).map(HalfBlock)
+
+    /// Parameters of the IS25LP/IS25WP part identified by `id`
+    pub fn from_jedec_id(id: &JedecId) -> Option<Self> {
+        Density::from_jedec_id(id).map(Self::new)
     }
-    pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_BLOCK)..((1 + self.0) * PAGES_PER_BLOCK)).map(Page)
+
+    pub fn density(&self) -> Density {
+        self.density
     }
-    pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+}
+
+impl Default for IS25xPParams {
+    fn default() -> Self {
+        Self::new(Density::Mbit128)
     }
-    pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
+}
+
+impl FlashParams for IS25xPParams {
+    fn page_size(&self) -> u32 {
+        PAGE_SIZE
     }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::blocks().find(|s| s.contains(address))
+
+    fn sector_size(&self) -> u32 {
+        SECTOR_SIZE
     }
-    pub const fn size() -> usize {
-        BLOCK_SIZE as usize
+
+    fn halfblock_size(&self) -> u32 {
+        HALFBLOCK_SIZE
     }
-}
 
-impl HalfBlock {
-    pub fn sectors(&self) -> impl Iterator<Item = Sector> {
-        ((self.0 * SECTORS_PER_HALFBLOCK)..((1 + self.0) * SECTORS_PER_HALFBLOCK)).map(Sector)
+    fn block_size(&self) -> u32 {
+        BLOCK_SIZE
     }
-    pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_HALFBLOCK)..((1 + self.0) * PAGES_PER_HALFBLOCK)).map(Page)
+
+    fn capacity(&self) -> u32 {
+        self.density.bytes()
     }
-    pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+
+    fn sector_erase_command(&self) -> QspiWriteCommand<'static> {
+        commands::ERASE_SECTOR
     }
-    pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
+
+    fn halfblock_erase_command(&self) -> QspiWriteCommand<'static> {
+        commands::ERASE_HALF_BLOCK
     }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::halfblocks().find(|s| s.contains(address))
+
+    fn block_erase_command(&self) -> QspiWriteCommand<'static> {
+        commands::ERASE_BLOCK
     }
-    pub const fn size() -> usize {
-        HALFBLOCK_SIZE as usize
+
+    fn read_command(&self, lanes: QspiMode) -> QspiReadCommand {
+        match lanes {
+            QspiMode::QuadChannel => commands::QUAD_READ,
+            _ => commands::READ,
+        }
+    }
+
+    fn program_command(&self, lanes: QspiMode) -> QspiWriteCommand<'static> {
+        match lanes {
+            QspiMode::QuadChannel => commands::QUAD_WRITE,
+            _ => commands::WRITE,
+        }
     }
-}
 
-impl Sector {
-    pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_SECTOR)..((1 + self.0) * PAGES_PER_SECTOR)).map(Page)
+    fn four_byte_opcode(&self, opcode: u8) -> Option<u8> {
+        commands::four_byte_opcode(opcode)
     }
-    pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+}
+
+/// Parameters discovered at runtime from the SFDP basic flash parameter table
+/// of the attached device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JedecAuto {
+    bfpt: BasicFlashParameters,
+    sector: EraseType,
+    halfblock: EraseType,
+    block: EraseType,
+}
+
+impl JedecAuto {
+    /// Select the erase commands from the erase types of `bfpt`. The smallest
+    /// erase type is used for sectors and the largest for blocks. Half blocks
+    /// use the second largest erase type, falling back to sectors on devices
+    /// with only two erase types.
+    pub fn from_sfdp(bfpt: BasicFlashParameters) -> Option<Self> {
+        let mut erase_types = bfpt.erase_types;
+        erase_types.sort_unstable_by_key(|e| e.map_or(u32::MAX, |e| e.size));
+        let count = erase_types.iter().flatten().count();
+
+        let sector = erase_types[0]?;
+        let block = erase_types[count - 1]?;
+        let halfblock = match count {
+            1 | 2 => sector,
+            _ => erase_types[count - 2]?,
+        };
+
+        Some(Self {
+            bfpt,
+            sector,
+            halfblock,
+            block,
+        })
     }
-    pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
+
+    pub fn basic_flash_parameters(&self) -> &BasicFlashParameters {
+        &self.bfpt
     }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::sectors().find(|s| s.contains(address))
+
+    fn erase_command(&self, erase: EraseType) -> QspiWriteCommand<'static> {
+        commands::ERASE_SECTOR.instruction(erase.opcode, QspiMode::SingleChannel)
     }
-    pub const fn size() -> usize {
-        SECTOR_SIZE as usize
+
+    /// Fastest read mode described by the table, as the read itself along
+    /// with the lanes used by the address and data phases
+    fn read_mode(&self, lanes: QspiMode) -> (FastRead, QspiMode, QspiMode) {
+        use QspiMode::*;
+
+        let bfpt = &self.bfpt;
+        let quad = lanes == QuadChannel && bfpt.quad_enable != QuadEnable::Unsupported;
+        let dual = lanes != SingleChannel;
+
+        let modes = [
+            (quad, bfpt.read_1_4_4, QuadChannel, QuadChannel),
+            (quad, bfpt.read_1_1_4, SingleChannel, QuadChannel),
+            (dual, bfpt.read_1_2_2, DualChannel, DualChannel),
+            (dual, bfpt.read_1_1_2, SingleChannel, DualChannel),
+        ];
+
+        modes
+            .iter()
+            .find_map(|&(usable, read, address, data)| {
+                read.filter(|_| usable).map(|read| (read, address, data))
+            })
+            .unwrap_or((
+                FastRead {
+                    opcode: 0x0B,
+                    dummy_cycles: 8,
+                },
+                SingleChannel,
+                SingleChannel,
+            ))
     }
 }
 
-impl Page {
-    pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+impl FlashParams for JedecAuto {
+    fn page_size(&self) -> u32 {
+        self.bfpt.page_size
     }
-    pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
+
+    fn sector_size(&self) -> u32 {
+        self.sector.size
     }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::pages().find(|s| s.contains(address))
+
+    fn halfblock_size(&self) -> u32 {
+        self.halfblock.size
     }
-    pub const fn size() -> usize {
-        PAGE_SIZE as usize
+
+    fn block_size(&self) -> u32 {
+        self.block.size
     }
-}
 
-impl Region for Block {
-    fn contains(&self, address: u32) -> bool {
-        let start = BLOCK_SIZE * self.0;
-        (start <= address) && (address < start + BLOCK_SIZE)
+    fn capacity(&self) -> u32 {
+        self.bfpt.capacity
     }
-}
 
-impl Region for HalfBlock {
-    fn contains(&self, address: u32) -> bool {
-        let start = HALFBLOCK_SIZE * self.0;
-        (start <= address) && (address < start + HALFBLOCK_SIZE)
+    fn sector_erase_command(&self) -> QspiWriteCommand<'static> {
+        self.erase_command(self.sector)
     }
-}
 
-impl Region for Sector {
-    fn contains(&self, address: u32) -> bool {
-        let start = SECTOR_SIZE * self.0;
-        (start <= address) && (address < start + SECTOR_SIZE)
+    fn halfblock_erase_command(&self) -> QspiWriteCommand<'static> {
+        self.erase_command(self.halfblock)
     }
-}
 
-impl Region for Page {
-    fn contains(&self, address: u32) -> bool {
-        let start = PAGE_SIZE * self.0;
-        (start <= address) && (address < start + PAGE_SIZE)
+    fn block_erase_command(&self) -> QspiWriteCommand<'static> {
+        self.erase_command(self.block)
     }
-}
 
-pub struct IS25xPParams;
+    fn read_command(&self, lanes: QspiMode) -> QspiReadCommand {
+        let (read, address_mode, data_mode) = self.read_mode(lanes);
 
-impl FlashParams for IS25xPParams {
-    
-}
\ No newline at end of file
+        QspiReadCommand {
+            instruction: Some((read.opcode, QspiMode::SingleChannel)),
+            address: Some((0, address_mode)),
+            address_size: AddressSize::Addr24Bit,
+            dummy_cycles: read.dummy_cycles,
+            data_mode,
+            receive_length: 0,
+            double_data_rate: false,
+        }
+    }
+
+    fn program_command(&self, _lanes: QspiMode) -> QspiWriteCommand<'static> {
+        // The basic flash parameter table does not describe any multi lane
+        // page program commands
+        commands::WRITE
+    }
+
+    fn quad_enable(&self) -> bool {
+        self.bfpt.quad_enable == QuadEnable::Status6
+    }
+
+    fn four_byte_entry(&self) -> Option<FourByteEntry> {
+        self.bfpt.four_byte_entry
+    }
+}
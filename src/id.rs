@@ -0,0 +1,120 @@
+/// JEDEC manufacturer ID of ISSI
+pub const ISSI: u8 = 0x9D;
+/// Memory type of the 3.0V IS25LP parts
+pub const IS25LP: u8 = 0x60;
+/// Memory type of the 1.8V IS25WP parts
+pub const IS25WP: u8 = 0x70;
+
+/// Identification bytes returned by RDID (0x9F)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JedecId([u8; 3]);
+
+impl From<[u8; 3]> for JedecId {
+    fn from(v: [u8; 3]) -> Self {
+        Self(v)
+    }
+}
+
+impl JedecId {
+    /// JEDEC assigned manufacturer ID (0x9D for ISSI)
+    pub fn manufacturer(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Memory type, distinguishing the IS25LP (0x60) and IS25WP (0x70) parts
+    pub fn memory_type(&self) -> u8 {
+        self.0[1]
+    }
+
+    /// Capacity code, where the density in bits is `2^capacity`
+    pub fn capacity(&self) -> u8 {
+        self.0[2]
+    }
+
+    /// Whether the ID was returned by a device, rather than read from an
+    /// idle or shorted data line
+    pub fn is_valid(&self) -> bool {
+        !matches!(self.manufacturer(), 0x00 | 0xFF)
+    }
+
+    /// Whether the ID belongs to a part of the ISSI IS25LP/IS25WP family
+    pub fn is_is25xp(&self) -> bool {
+        self.manufacturer() == ISSI && matches!(self.memory_type(), IS25LP | IS25WP)
+    }
+}
+
+/// Densities available in the IS25LP/IS25WP family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Density {
+    Mbit16,
+    Mbit32,
+    Mbit64,
+    Mbit128,
+    Mbit256,
+    Mbit512,
+}
+
+impl Density {
+    /// Density encoded in the capacity byte of an IS25LP/IS25WP JEDEC ID
+    pub fn from_jedec_id(id: &JedecId) -> Option<Self> {
+        if !id.is_is25xp() {
+            return None;
+        }
+
+        match id.capacity() {
+            0x15 => Some(Density::Mbit16),
+            0x16 => Some(Density::Mbit32),
+            0x17 => Some(Density::Mbit64),
+            0x18 => Some(Density::Mbit128),
+            0x19 => Some(Density::Mbit256),
+            0x1A => Some(Density::Mbit512),
+            _ => None,
+        }
+    }
+
+    /// Size of the memory array in bytes
+    pub const fn bytes(&self) -> u32 {
+        match self {
+            Density::Mbit16 => 2 * 1024 * 1024,
+            Density::Mbit32 => 4 * 1024 * 1024,
+            Density::Mbit64 => 8 * 1024 * 1024,
+            Density::Mbit128 => 16 * 1024 * 1024,
+            Density::Mbit256 => 32 * 1024 * 1024,
+            Density::Mbit512 => 64 * 1024 * 1024,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let id = JedecId::from([0x9D, 0x60, 0x18]);
+        assert_eq!(id.manufacturer(), ISSI);
+        assert_eq!(id.memory_type(), IS25LP);
+        assert_eq!(id.capacity(), 0x18);
+        assert!(id.is_is25xp());
+
+        assert!(JedecId::from([0x9D, 0x70, 0x17]).is_is25xp());
+        assert!(!JedecId::from([0xEF, 0x40, 0x18]).is_is25xp());
+        assert!(!JedecId::from([0x9D, 0x40, 0x18]).is_is25xp());
+        assert!(!JedecId::from([0xFF, 0xFF, 0xFF]).is_is25xp());
+    }
+
+    #[test]
+    fn density() {
+        let density = |id| Density::from_jedec_id(&JedecId::from(id));
+
+        assert_eq!(density([0x9D, 0x60, 0x15]), Some(Density::Mbit16));
+        assert_eq!(density([0x9D, 0x70, 0x18]), Some(Density::Mbit128));
+        assert_eq!(density([0x9D, 0x60, 0x1A]), Some(Density::Mbit512));
+        assert_eq!(density([0x9D, 0x60, 0x14]), None);
+        assert_eq!(density([0xEF, 0x40, 0x18]), None);
+
+        assert_eq!(Density::Mbit16.bytes(), 0x20_0000);
+        assert_eq!(Density::Mbit128.bytes(), 0x100_0000);
+        assert_eq!(Density::Mbit512.bytes(), 0x400_0000);
+    }
+}
@@ -2,426 +2,1936 @@
 
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(feature = "async")]
+pub mod asynch;
+mod checksum;
 pub mod commands;
-// mod flash_params;
+mod flash_params;
+mod id;
+mod protection;
+pub mod qspi;
+pub mod sfdp;
+pub mod spi;
 mod status;
+#[cfg(feature = "stm32l4xx")]
+mod stm32l4xx;
 
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "async")]
+pub use asynch::{AsyncQspi, IS25xPAsync};
+pub use checksum::{Checksum, Crc16, Crc32};
+pub use flash_params::{FlashParams, FourByteEntry, IS25xPParams, JedecAuto, Timeouts};
+pub use id::{Density, JedecId};
+pub use protection::{BlockProtection, ProtectionArea};
+pub use qspi::{AddressSize, Qspi, QspiMode, QspiReadCommand, QspiWriteCommand};
+pub use status::{DriveStrength, ExtendedReadParameters, ReadParameters, WrapLength};
+
+use core::{fmt, ops::Range};
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin};
 use embedded_storage::{
-    nor_flash::{MultiwriteNorFlash, NorFlash, ReadNorFlash},
+    nor_flash::{
+        ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+    },
     Region,
 };
-use status::{Status, QE};
-use stm32l4xx_hal::{
-    pac::QUADSPI,
-    qspi::{
-        ClkPin, IO0Pin, IO1Pin, IO2Pin, IO3Pin, NCSPin, QspiError, QspiMode, QspiReadCommand,
-        QspiWriteCommand,
+use flash_params::SECTOR_SIZE;
+use sfdp::{BasicFlashParameters, ParameterHeader};
+use status::{Function, Status, IRL0, QE, SRWD, TBS};
+
+/// Errors of the driver, carrying the error `E` of the underlying transport
+#[derive(Debug)]
+pub enum Error<E> {
+    Busy,
+    /// The transport failed to carry out a command
+    Qspi(E),
+    OutOfBounds,
+    Alignment,
+    Size,
+    /// The attached device is not an ISSI IS25LP/IS25WP part
+    UnknownDevice(JedecId),
+    /// The attached device does not expose a usable SFDP basic flash
+    /// parameter table
+    Sfdp,
+    /// A one time programmable bit is already programmed and cannot be
+    /// cleared
+    Otp,
+    /// Driving the WP# pin failed
+    Pin,
+    /// The operation is not allowed while a program or erase is suspended
+    Suspended,
+    /// The addressed region is protected against program and erase, or the
+    /// device dropped the command
+    WriteProtected,
+    /// The device did not complete an operation within its maximum duration
+    Timeout,
+    /// The transport cannot drive the lanes of the requested mode, or the
+    /// operation is not possible in the current configuration of the device
+    Unsupported,
+    /// Reading back a programmed or erased region found `address` not
+    /// holding the intended value
+    VerifyFailed {
+        address: u32,
     },
-};
+    /// The device flagged the last program as failed
+    ProgramFailed,
+    /// The device flagged the last erase as failed
+    EraseFailed,
+    /// The device flagged the last program or erase as targeting a protected
+    /// region
+    ProtectionError,
+}
+
+/// How the memory array is addressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// 3 byte addresses, reaching the first 16 MiB
+    ThreeByte,
+    /// 4 byte addresses using the dedicated 4 byte opcodes
+    FourByteOpcodes,
+    /// 4 byte addresses using the regular opcodes, after entering 4 byte
+    /// address mode on devices not taking them all along
+    FourByteMode,
+}
+
+/// Largest memory array reachable with 3 byte addresses
+const THREE_BYTE_LIMIT: u32 = 16 * 1024 * 1024;
+
+impl AddressMode {
+    /// Addressing of the device described by `params`. The dedicated 4 byte
+    /// opcodes are preferred, as they leave the device in the 3 byte address
+    /// mode expected after a reset, but only when every array command has
+    /// one.
+    fn select<P: FlashParams>(params: &P, lanes: QspiMode) -> Self {
+        let commands = [
+            params.read_command(lanes).instruction,
+            params.program_command(lanes).instruction,
+            params.sector_erase_command().instruction,
+            params.halfblock_erase_command().instruction,
+            params.block_erase_command().instruction,
+        ];
+        if params.four_byte_entry() == Some(FourByteEntry::Always) {
+            AddressMode::FourByteMode
+        } else if params.capacity() <= THREE_BYTE_LIMIT {
+            AddressMode::ThreeByte
+        } else if commands
+            .iter()
+            .flatten()
+            .all(|&(i, _)| params.four_byte_opcode(i).is_some())
+        {
+            AddressMode::FourByteOpcodes
+        } else {
+            AddressMode::FourByteMode
+        }
+    }
+
+    /// Opcode and address width of an array command, or `None` if it has no
+    /// 4 byte opcode to go with a 4 byte address
+    fn addressing<P: FlashParams>(self, params: &P, opcode: u8) -> Option<(u8, AddressSize)> {
+        match self {
+            AddressMode::ThreeByte => Some((opcode, AddressSize::Addr24Bit)),
+            AddressMode::FourByteOpcodes => params
+                .four_byte_opcode(opcode)
+                .map(|opcode| (opcode, AddressSize::Addr32Bit)),
+            AddressMode::FourByteMode => Some((opcode, AddressSize::Addr32Bit)),
+        }
+    }
+
+    /// Address width of commands without a 4 byte opcode, which follow the
+    /// address mode of the device
+    fn address_size(self) -> AddressSize {
+        match self {
+            AddressMode::FourByteMode => AddressSize::Addr32Bit,
+            _ => AddressSize::Addr24Bit,
+        }
+    }
+
+    /// Complete a read command of the memory array with `address`
+    fn read_at<P: FlashParams>(
+        self,
+        params: &P,
+        mut cmd: QspiReadCommand,
+        address: u32,
+    ) -> Option<QspiReadCommand> {
+        if let Some((opcode, mode)) = cmd.instruction {
+            let (opcode, address_size) = self.addressing(params, opcode)?;
+            cmd.instruction = Some((opcode, mode));
+            cmd.address_size = address_size;
+        }
+        Some(cmd.with_address(address))
+    }
+
+    /// Complete a program or erase command of the memory array with `address`
+    fn write_at<'a, P: FlashParams>(
+        self,
+        params: &P,
+        mut cmd: QspiWriteCommand<'a>,
+        address: u32,
+    ) -> Option<QspiWriteCommand<'a>> {
+        if let Some((opcode, mode)) = cmd.instruction {
+            let (opcode, address_size) = self.addressing(params, opcode)?;
+            cmd.instruction = Some((opcode, mode));
+            cmd.address_size = address_size;
+        }
+        Some(cmd.with_address(address))
+    }
+}
 
-pub trait Qspi {
-    type Error: core::fmt::Debug;
+/// Command used to read the memory array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Normal read (NORD, 0x03), limited to lower clock frequencies
+    Normal,
+    /// Fast read (FRD, 0x0B)
+    Fast,
+    /// Fast read dual output (FRDO, 0x3B)
+    DualOutput,
+    /// Fast read dual I/O (FRDIO, 0xBB)
+    DualIo,
+    /// Fast read quad output (FRQO, 0x6B)
+    QuadOutput,
+    /// Fast read quad I/O (FRQIO, 0xEB)
+    QuadIo,
+    /// Fast read DTR (FRDTR, 0x0D)
+    FastDtr,
+    /// Fast read dual I/O DTR (FRDDTR, 0xBD)
+    DualIoDtr,
+    /// Fast read quad I/O DTR (FRQDTR, 0xED)
+    QuadIoDtr,
+}
+
+impl ReadMode {
+    const ALL: [ReadMode; 9] = [
+        ReadMode::Normal,
+        ReadMode::Fast,
+        ReadMode::DualOutput,
+        ReadMode::DualIo,
+        ReadMode::QuadOutput,
+        ReadMode::QuadIo,
+        ReadMode::FastDtr,
+        ReadMode::DualIoDtr,
+        ReadMode::QuadIoDtr,
+    ];
+
+    /// Mode of the read command `cmd`, or `None` if it is not one of the
+    /// read commands of the IS25LP/IS25WP
+    fn from_command(cmd: &QspiReadCommand) -> Option<Self> {
+        let opcode = cmd.instruction.map(|(opcode, _)| opcode);
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.command().instruction.map(|(opcode, _)| opcode) == opcode)
+    }
+
+    /// Read command, with the default number of dummy cycles of the mode
+    pub fn command(self) -> QspiReadCommand {
+        match self {
+            ReadMode::Normal => commands::NORMAL_READ,
+            ReadMode::Fast => commands::READ,
+            ReadMode::DualOutput => commands::DUAL_OUTPUT_READ,
+            ReadMode::DualIo => commands::DUAL_IO_READ,
+            ReadMode::QuadOutput => commands::QUAD_OUTPUT_READ,
+            ReadMode::QuadIo => commands::QUAD_READ,
+            ReadMode::FastDtr => commands::DTR_READ,
+            ReadMode::DualIoDtr => commands::DUAL_IO_DTR_READ,
+            ReadMode::QuadIoDtr => commands::QUAD_IO_DTR_READ,
+        }
+    }
+
+    /// Whether the read command is available in QPI mode
+    pub fn qpi(self) -> bool {
+        matches!(
+            self,
+            ReadMode::Fast | ReadMode::QuadIo | ReadMode::FastDtr | ReadMode::QuadIoDtr
+        )
+    }
+
+    /// Whether the address and data phases are transferred on both clock
+    /// edges
+    pub fn double_data_rate(self) -> bool {
+        matches!(
+            self,
+            ReadMode::FastDtr | ReadMode::DualIoDtr | ReadMode::QuadIoDtr
+        )
+    }
+
+    /// Widest phase of the read command
+    pub fn lanes(self) -> QspiMode {
+        match self {
+            ReadMode::Normal | ReadMode::Fast | ReadMode::FastDtr => QspiMode::SingleChannel,
+            ReadMode::DualOutput | ReadMode::DualIo | ReadMode::DualIoDtr => QspiMode::DualChannel,
+            ReadMode::QuadOutput | ReadMode::QuadIo | ReadMode::QuadIoDtr => QspiMode::QuadChannel,
+        }
+    }
+}
 
-    fn write(&self, cmd: QspiWriteCommand) -> Result<(), Self::Error>;
-    fn transfer(&self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error>;
+/// Interval between status reads while waiting for an operation to complete
+const POLL_INTERVAL_US: u32 = 10;
+
+/// Number of bytes read at once when verifying programs and erases, or
+/// checking whether a region is blank
+const READ_BACK_CHUNK_SIZE: usize = 64;
+
+/// Number of bytes read at once when computing a checksum
+const CHECKSUM_BURST_SIZE: usize = 512;
+
+/// Number of information rows
+pub const OTP_ROWS: u8 = 4;
+
+/// Size of an information row in bytes
+pub const OTP_ROW_SIZE: u32 = 256;
+
+/// Program or erase started without waiting for its completion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Program of the addresses `start..end`
+    Program { start: u32, end: u32 },
+    /// Erase of the addresses `start..end`
+    Erase { start: u32, end: u32 },
 }
 
-impl<CLK, NCS, IO0, IO1, IO2, IO3> Qspi
-    for stm32l4xx_hal::qspi::Qspi<(CLK, NCS, IO0, IO1, IO2, IO3)>
+impl Operation {
+    /// Addresses affected by the operation
+    pub fn range(&self) -> Range<u32> {
+        match *self {
+            Operation::Program { start, end } | Operation::Erase { start, end } => start..end,
+        }
+    }
+}
+
+fn overlaps(a: &Range<u32>, b: &Range<u32>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Addresses of `len` bytes from `offset`, failing with
+/// [`Error::OutOfBounds`] past the end of the address space
+fn byte_range<E>(offset: u32, len: usize) -> Result<Range<u32>, Error<E>> {
+    u32::try_from(len)
+        .ok()
+        .and_then(|len| offset.checked_add(len))
+        .map(|end| offset..end)
+        .ok_or(Error::OutOfBounds)
+}
+
+/// Fail with [`Error::WriteProtected`] if any address in `range` is protected
+/// by the block protection bits of `status`, on a device with IS25LP/IS25WP
+/// block protection counted from `tbs`
+fn check_protection<E>(
+    tbs: Option<ProtectionArea>,
+    map: &MemoryMap,
+    status: &Status,
+    range: Range<u32>,
+) -> Result<(), Error<E>> {
+    let protected = tbs.and_then(|area| status.bp().range(area, map));
+    match protected {
+        Some(p) if overlaps(&p, &range) => Err(Error::WriteProtected),
+        _ => Ok(()),
+    }
+}
+
+/// Error flagged in the extended read register by the last program or erase
+fn flagged_error<E>(erp: &ExtendedReadParameters) -> Option<Error<E>> {
+    if erp.prot_e() {
+        Some(Error::ProtectionError)
+    } else if erp.p_err() {
+        Some(Error::ProgramFailed)
+    } else if erp.e_err() {
+        Some(Error::EraseFailed)
+    } else {
+        None
+    }
+}
+
+/// Largest region erased in one command by [`NorFlash::erase`]
+enum EraseRegion {
+    Chip,
+    Block(Block),
+    HalfBlock(HalfBlock),
+    Sector(Sector),
+}
+
+/// Delay source of drivers constructed without one, which wait for
+/// operations to complete without a timeout
+#[derive(Debug, Clone, Copy)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+pub struct IS25xP<Q, P = IS25xPParams, D = NoDelay> {
+    qspi: Q,
+    id: JedecId,
+    params: P,
+    map: MemoryMap,
+    address_mode: AddressMode,
+    /// Area selected by the TBS bit, on devices with IS25LP/IS25WP block
+    /// protection
+    tbs: Option<ProtectionArea>,
+    /// Operation started without waiting for its completion
+    operation: Option<Operation>,
+    suspended: Option<Operation>,
+    /// Maximum duration of the started operation, in microseconds
+    timeout: u32,
+    /// Maximum duration of the suspended operation, in microseconds
+    suspended_timeout: u32,
+    /// Delay between status reads, measuring timeouts
+    delay: Option<D>,
+    /// Read back programmed pages and erased regions
+    verify: bool,
+    /// Read command selected in place of the one of the parameters
+    read_mode: Option<ReadMode>,
+    /// Whether the device is in QPI mode, taking every phase of every
+    /// command on four lanes
+    qpi: bool,
+    /// Last read parameters read from or written to the device
+    read_parameters: ReadParameters,
+}
+
+impl<Q> IS25xP<Q, IS25xPParams>
 where
-    CLK: ClkPin<QUADSPI>,
-    NCS: NCSPin<QUADSPI>,
-    IO0: IO0Pin<QUADSPI>,
-    IO1: IO1Pin<QUADSPI>,
-    IO2: IO2Pin<QUADSPI>,
-    IO3: IO3Pin<QUADSPI>,
+    Q: Qspi,
 {
-    type Error = QspiError;
+    /// Initialize the driver, with the geometry selected from the density
+    /// reported by the attached device
+    pub fn try_new(qspi: Q) -> Result<Self, Error<Q::Error>> {
+        Self::detect(qspi, None)
+    }
+
+    /// Initialize the driver, overriding the density reported by the
+    /// attached device
+    pub fn try_new_with_density(qspi: Q, density: Density) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, IS25xPParams::new(density), None)?;
+        if !flash.id.is_is25xp() {
+            return Err(Error::UnknownDevice(flash.id));
+        }
+        flash.configure()?;
+        Ok(flash)
+    }
+}
 
-    fn write(&self, cmd: QspiWriteCommand) -> Result<(), Self::Error> {
-        stm32l4xx_hal::qspi::Qspi::write(self, cmd)
+impl<Q, D> IS25xP<Q, IS25xPParams, D>
+where
+    Q: Qspi,
+    D: DelayNs,
+{
+    /// Initialize the driver like [`IS25xP::try_new`], measuring the
+    /// duration of operations with `delay` and failing with
+    /// [`Error::Timeout`] once they exceed their maximum duration
+    pub fn try_new_with_delay(qspi: Q, delay: D) -> Result<Self, Error<Q::Error>> {
+        Self::detect(qspi, Some(delay))
     }
 
-    fn transfer(&self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
-        stm32l4xx_hal::qspi::Qspi::transfer(self, cmd, buf)
+    fn detect(qspi: Q, delay: Option<D>) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, IS25xPParams::default(), delay)?;
+        let params =
+            IS25xPParams::from_jedec_id(&flash.id).ok_or(Error::UnknownDevice(flash.id))?;
+        flash.set_params(params);
+        flash.configure()?;
+        Ok(flash)
     }
 }
 
-#[derive(Debug)]
-pub enum Error {
-    Busy,
-    Qspi,
-    OutOfBounds,
-    Alignment,
-    Size,
+impl<Q> IS25xP<Q, JedecAuto>
+where
+    Q: Qspi,
+{
+    /// Initialize the driver, with the geometry and commands discovered from
+    /// the SFDP tables of the attached device. This also supports devices
+    /// from other manufacturers.
+    pub fn try_new_auto(qspi: Q) -> Result<Self, Error<Q::Error>> {
+        let mut flash = IS25xP::probe(qspi, IS25xPParams::default(), None)?;
+        let bfpt = flash.read_basic_flash_parameters()?;
+        let params = JedecAuto::from_sfdp(bfpt).ok_or(Error::Sfdp)?;
+
+        let mut flash = IS25xP {
+            qspi: flash.qspi,
+            id: flash.id,
+            map: MemoryMap::new(&params),
+            params,
+            address_mode: AddressMode::ThreeByte,
+            tbs: None,
+            operation: None,
+            suspended: None,
+            timeout: 0,
+            suspended_timeout: 0,
+            delay: flash.delay,
+            verify: false,
+            read_mode: None,
+            qpi: flash.qpi,
+            read_parameters: flash.read_parameters,
+        };
+        flash.configure()?;
+        Ok(flash)
+    }
 }
 
-pub struct IS25xP<Q> {
-    qspi: Q,
+impl<Q, P> IS25xP<Q, P>
+where
+    Q: Qspi,
+    P: FlashParams,
+{
+    /// Initialize the driver for a device described by `params`, without
+    /// checking the identity of the attached device
+    pub fn try_new_with_params(qspi: Q, params: P) -> Result<Self, Error<Q::Error>> {
+        let mut flash = Self::probe(qspi, params, None)?;
+        flash.configure()?;
+        Ok(flash)
+    }
 }
 
-impl<Q> IS25xP<Q>
+impl<Q, P, D> IS25xP<Q, P, D>
 where
     Q: Qspi,
+    P: FlashParams,
+    D: DelayNs,
 {
-    pub fn try_new(qspi: Q) -> Result<Self, Error> {
-        let flash = IS25xP { qspi };
+    fn probe(qspi: Q, params: P, delay: Option<D>) -> Result<Self, Error<Q::Error>> {
+        let map = MemoryMap::new(&params);
+        let mut flash = IS25xP {
+            qspi,
+            id: JedecId::from([0; 3]),
+            params,
+            map,
+            address_mode: AddressMode::ThreeByte,
+            tbs: None,
+            operation: None,
+            suspended: None,
+            timeout: 0,
+            suspended_timeout: 0,
+            delay,
+            verify: false,
+            read_mode: None,
+            qpi: false,
+            read_parameters: ReadParameters::new(),
+        };
+        flash.recover_from_qpi()?;
         flash.wait_busy()?;
-        // Set quad enable bit
-        flash
-            .qspi
-            .write(commands::WRITE_STATUS.data(&[QE], QspiMode::SingleChannel))
-            .map_err(|_| Error::Qspi)?;
-
-        // Apply QPI mode - This feature does not work..
-        // flash.qspi.write(commands::QPI_ENABLE).map_err(|_| Error::Qspi)?;
-        // flash
-        //     .qspi
-        //     .apply_config(flash.qspi.get_config().qpi_mode(true));
-
+        flash.id = flash.read_jedec_id()?;
         Ok(flash)
     }
 
-    fn status(&self) -> Result<Status, Error> {
+    /// Return a device left in QPI mode, e.g. by a warm reset of the host,
+    /// to SPI mode. Such a device does not answer commands sent on a single
+    /// lane, but does answer the QPI read of its JEDEC ID.
+    fn recover_from_qpi(&mut self) -> Result<(), Error<Q::Error>> {
+        if self.qspi.lanes() != QspiMode::QuadChannel || self.read_jedec_id()?.is_valid() {
+            return Ok(());
+        }
+
+        self.qpi = true;
+        if self.read_jedec_id()?.is_valid() {
+            self.wait_busy()?;
+            self.exit_qpi()
+        } else {
+            self.qpi = false;
+            Ok(())
+        }
+    }
+
+    fn set_params(&mut self, params: P) {
+        self.map = MemoryMap::new(&params);
+        self.params = params;
+    }
+
+    fn configure(&mut self) -> Result<(), Error<Q::Error>> {
+        // The layout of the protection bits differs between manufacturers
+        self.tbs = if self.id.is_is25xp() {
+            Some(self.protection_area()?)
+        } else {
+            None
+        };
+
+        // Pick up read parameters set before a warm reset of the host
+        if self.id.is_is25xp() {
+            self.read_parameters()?;
+        }
+
+        self.address_mode = AddressMode::select(&self.params, self.qspi.lanes());
+        if self.address_mode == AddressMode::FourByteMode {
+            let entry = self.params.four_byte_entry().ok_or(Error::Unsupported)?;
+            for &cmd in entry.commands() {
+                self.command(cmd)?;
+            }
+        }
+
+        // Set quad enable bit, unless the transport can only drive a single
+        // lane, in which case IO2/IO3 keep their WP#/HOLD# functions. The
+        // remaining bits are preserved, keeping any block protection in place.
+        if self.quad() && self.params.quad_enable() {
+            let status = self.status()?;
+            if !status.qe() {
+                self.write_status(status.bits() | QE)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Measure the duration of operations with `delay`, failing with
+    /// [`Error::Timeout`] once they exceed their maximum duration
+    pub fn with_delay<D2: DelayNs>(self, delay: D2) -> IS25xP<Q, P, D2> {
+        IS25xP {
+            qspi: self.qspi,
+            id: self.id,
+            params: self.params,
+            map: self.map,
+            address_mode: self.address_mode,
+            tbs: self.tbs,
+            operation: self.operation,
+            suspended: self.suspended,
+            timeout: self.timeout,
+            suspended_timeout: self.suspended_timeout,
+            delay: Some(delay),
+            verify: self.verify,
+            read_mode: self.read_mode,
+            qpi: self.qpi,
+            read_parameters: self.read_parameters,
+        }
+    }
+
+    /// Geometry and command set in use
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    /// JEDEC ID read from the device during [`IS25xP::try_new`]
+    pub fn jedec_id(&self) -> JedecId {
+        self.id
+    }
+
+    /// Read the manufacturer ID, memory type and capacity (RDID)
+    pub fn read_jedec_id(&mut self) -> Result<JedecId, Error<Q::Error>> {
+        let cmd = match self.qpi {
+            true => commands::READ_JEDEC_ID_QPI,
+            false => commands::READ_JEDEC_ID,
+        };
+
+        let mut id = [0u8; 3];
+        self.transfer(cmd, &mut id)?;
+
+        Ok(id.into())
+    }
+
+    /// Read the manufacturer and device ID (RDMDID), returned in that order
+    pub fn read_manufacturer_id(&mut self) -> Result<(u8, u8), Error<Q::Error>> {
+        let mut id = [0u8; 2];
+        self.transfer(commands::READ_MANUFACTURER_ID, &mut id)?;
+
+        Ok((id[0], id[1]))
+    }
+
+    /// Read the 16 byte factory programmed unique ID (RDUID)
+    pub fn unique_id(&mut self) -> Result<[u8; 16], Error<Q::Error>> {
+        let mut id = [0u8; 16];
+        let mut cmd = commands::READ_UNIQUE_ID;
+        cmd.address_size = self.address_mode.address_size();
+        self.transfer(cmd, &mut id)?;
+
+        Ok(id)
+    }
+
+    /// Read from the Serial Flash Discoverable Parameters space (RDSFDP)
+    pub fn read_sfdp(&mut self, address: u32, data: &mut [u8]) -> Result<(), Error<Q::Error>> {
+        self.transfer(
+            commands::READ_SFDP
+                .with_address(address)
+                .receive_length(data.len() as u32),
+            data,
+        )
+    }
+
+    /// Locate and parse the SFDP basic flash parameter table
+    pub fn read_basic_flash_parameters(&mut self) -> Result<BasicFlashParameters, Error<Q::Error>> {
+        let mut header = [0u8; 8];
+        self.read_sfdp(0, &mut header)?;
+        let headers = sfdp::parse_header(&header).ok_or(Error::Sfdp)?;
+
+        for i in 0..headers as u32 {
+            self.read_sfdp(8 + 8 * i, &mut header)?;
+            let header = ParameterHeader::from(header);
+            if header.id != sfdp::BASIC_FLASH_PARAMETERS {
+                continue;
+            }
+
+            let length = header.length.min(sfdp::BASIC_FLASH_PARAMETERS_LEN);
+            let mut table = [0u8; sfdp::BASIC_FLASH_PARAMETERS_LEN * 4];
+            self.read_sfdp(header.pointer, &mut table[..length * 4])?;
+
+            let mut dwords = [0u32; sfdp::BASIC_FLASH_PARAMETERS_LEN];
+            for (dw, bytes) in dwords.iter_mut().zip(table.chunks_exact(4)) {
+                *dw = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+
+            return BasicFlashParameters::parse(&dwords[..length]).ok_or(Error::Sfdp);
+        }
+
+        Err(Error::Sfdp)
+    }
+
+    /// Layout of the memory array of the attached device
+    pub fn memory_map(&self) -> MemoryMap {
+        self.map
+    }
+
+    /// Addressing used for the memory array, selected from its capacity
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    /// Whether programmed pages and erased regions are read back
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// Read command selected with [`IS25xP::set_read_mode`], or `None` while
+    /// the fastest read command of the parameters is used
+    pub fn read_mode(&self) -> Option<ReadMode> {
+        self.read_mode
+    }
+
+    /// Read the memory array with `mode`, and program pages with the single
+    /// or quad page program matching its lanes. Fails with
+    /// [`Error::Unsupported`] if the transport cannot drive the lanes of
+    /// `mode` or transfer at double data rate as DTR modes require.
+    pub fn set_read_mode(&mut self, mode: ReadMode) -> Result<(), Error<Q::Error>> {
+        if mode.lanes() > self.qspi.lanes()
+            || (mode.double_data_rate() && !self.qspi.double_data_rate())
+            || (self.qpi && !mode.qpi())
+        {
+            return Err(Error::Unsupported);
+        }
+
+        self.read_mode = Some(mode);
+        Ok(())
+    }
+
+    /// Whether the device is in QPI mode
+    pub fn qpi(&self) -> bool {
+        self.qpi
+    }
+
+    /// Enter QPI mode (QPIEN), in which the instruction, address and data
+    /// phases of every command use four lanes. Fails with
+    /// [`Error::Unsupported`] if the transport cannot drive four lanes, or if
+    /// the read mode in use has no QPI counterpart.
+    pub fn enter_qpi(&mut self) -> Result<(), Error<Q::Error>> {
+        if self.qpi {
+            return Ok(());
+        }
+
+        let read_mode = self
+            .read_mode
+            .or_else(|| ReadMode::from_command(&self.params.read_command(self.qspi.lanes())));
+        if !self.quad() || !read_mode.is_some_and(ReadMode::qpi) {
+            return Err(Error::Unsupported);
+        }
+
+        self.wait_busy()?;
+        self.command(commands::QPI_ENABLE)?;
+        self.qpi = true;
+        Ok(())
+    }
+
+    /// Return to SPI mode (QPIDI)
+    pub fn exit_qpi(&mut self) -> Result<(), Error<Q::Error>> {
+        if !self.qpi {
+            return Ok(());
+        }
+
+        self.wait_busy()?;
+        self.command(commands::QPI_DISABLE)?;
+        self.qpi = false;
+        Ok(())
+    }
+
+    /// Send `cmd`, with every phase on four lanes in QPI mode
+    fn command(&mut self, mut cmd: QspiWriteCommand) -> Result<(), Error<Q::Error>> {
+        if self.qpi {
+            cmd.instruction = cmd.instruction.map(|(i, _)| (i, QspiMode::QuadChannel));
+            cmd.address = cmd.address.map(|(a, _)| (a, QspiMode::QuadChannel));
+            cmd.data = cmd.data.map(|(d, _)| (d, QspiMode::QuadChannel));
+        }
+        self.qspi.write(cmd).map_err(Error::Qspi)
+    }
+
+    /// Send `cmd` and receive its data into `buf`, with every phase on four
+    /// lanes in QPI mode
+    fn transfer(
+        &mut self,
+        mut cmd: QspiReadCommand,
+        buf: &mut [u8],
+    ) -> Result<(), Error<Q::Error>> {
+        if self.qpi {
+            cmd.instruction = cmd.instruction.map(|(i, _)| (i, QspiMode::QuadChannel));
+            cmd.address = cmd.address.map(|(a, _)| (a, QspiMode::QuadChannel));
+            cmd.data_mode = QspiMode::QuadChannel;
+        }
+        self.qspi.transfer(cmd, buf).map_err(Error::Qspi)
+    }
+
+    /// Read command of the memory array, with the dummy cycles selected by
+    /// the read parameters
+    fn read_command(&self) -> QspiReadCommand {
+        let mut cmd = match self.read_mode {
+            Some(mode) => mode.command(),
+            None => self.params.read_command(self.qspi.lanes()),
+        };
+
+        let dummy_cycles = self.read_parameters.dummy_cycles();
+        match cmd.instruction {
+            // The normal read does not take any dummy cycles
+            Some((0x03, _)) => {}
+            _ if dummy_cycles != 0 => cmd.dummy_cycles = dummy_cycles,
+            // The fast reads take fewer dummy cycles in QPI mode
+            Some((0x0B | 0x0D, _)) if self.qpi => cmd.dummy_cycles = 6,
+            _ => {}
+        }
+        cmd
+    }
+
+    /// Read the read register (RDRP)
+    pub fn read_parameters(&mut self) -> Result<ReadParameters, Error<Q::Error>> {
+        let mut rp = [0u8; 1];
+        self.transfer(commands::READ_READ_PARAMETERS, &mut rp)?;
+
+        self.read_parameters = rp[0].into();
+        Ok(self.read_parameters)
+    }
+
+    /// Write the volatile read register (SRP). Reads of the memory array
+    /// use the dummy cycles of `params` from then on, which have to be enough
+    /// for the clock frequency of the bus.
+    pub fn set_read_parameters(&mut self, params: ReadParameters) -> Result<(), Error<Q::Error>> {
+        self.command(
+            commands::SET_READ_PARAMETERS.data(&[params.bits()], QspiMode::SingleChannel),
+        )?;
+
+        self.read_parameters = params;
+        Ok(())
+    }
+
+    /// Output driver strength, or `None` if the ODS bits of the extended read
+    /// register hold a reserved value
+    pub fn drive_strength(&mut self) -> Result<Option<DriveStrength>, Error<Q::Error>> {
+        Ok(self.extended_read_parameters()?.drive_strength())
+    }
+
+    /// Set the output driver strength in the volatile extended read register
+    /// (SERP)
+    pub fn set_drive_strength(&mut self, strength: DriveStrength) -> Result<(), Error<Q::Error>> {
+        self.check_is25xp()?;
+        let erp = self
+            .extended_read_parameters()?
+            .with_drive_strength(strength);
+        self.command(
+            commands::SET_EXTENDED_READ_PARAMETERS.data(&[erp.bits()], QspiMode::SingleChannel),
+        )
+    }
+
+    /// Read the extended read register (RDERP)
+    pub fn extended_read_parameters(&mut self) -> Result<ExtendedReadParameters, Error<Q::Error>> {
+        let mut erp = [0u8; 1];
+        self.transfer(commands::READ_EXTENDED_READ_PARAMETERS, &mut erp)?;
+        Ok(erp[0].into())
+    }
+
+    /// Fail with the error flagged in the extended read register by the last
+    /// program or erase. The flags stick until cleared (CLERP), which is done
+    /// before returning so they do not fail the next operation.
+    fn check_error_flags(&mut self) -> Result<(), Error<Q::Error>> {
+        // Only ISSI parts have the error flags
+        if !self.id.is_is25xp() {
+            return Ok(());
+        }
+
+        let erp = self.extended_read_parameters()?;
+        let Some(error) = flagged_error(&erp) else {
+            return Ok(());
+        };
+
+        self.command(commands::CLEAR_EXTENDED_READ_PARAMETERS)?;
+        Err(error)
+    }
+
+    /// Page program command matching the read command. QPI mode only has
+    /// the single lane page program, which then takes four lanes.
+    fn program_command(&self) -> QspiWriteCommand<'static> {
+        let lanes = match self.read_mode {
+            _ if self.qpi => QspiMode::SingleChannel,
+            Some(mode) => mode.lanes(),
+            None => self.qspi.lanes(),
+        };
+        self.params.program_command(lanes)
+    }
+
+    /// Read back every page programmed by [`IS25xP::write_page`] and every
+    /// region erased by the `erase_*` methods, failing with
+    /// [`Error::VerifyFailed`] at the first byte not holding the intended
+    /// value
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Address of `offset` within the information row `row`, checking that
+    /// `len` bytes from there fit in the row
+    fn otp_address(&self, row: u8, offset: u32, len: usize) -> Result<u32, Error<Q::Error>> {
+        if row >= OTP_ROWS || offset as usize + len > OTP_ROW_SIZE as usize {
+            return Err(Error::OutOfBounds);
+        }
+
+        // Information rows are 4 KiB apart
+        Ok(row as u32 * 0x1000 + offset)
+    }
+
+    /// Complete a read command of the memory array with `address`
+    fn read_at(
+        &self,
+        cmd: QspiReadCommand,
+        address: u32,
+    ) -> Result<QspiReadCommand, Error<Q::Error>> {
+        self.address_mode
+            .read_at(&self.params, cmd, address)
+            .ok_or(Error::Unsupported)
+    }
+
+    /// Complete a program or erase command of the memory array with `address`
+    fn write_at<'a>(
+        &self,
+        cmd: QspiWriteCommand<'a>,
+        address: u32,
+    ) -> Result<QspiWriteCommand<'a>, Error<Q::Error>> {
+        self.address_mode
+            .write_at(&self.params, cmd, address)
+            .ok_or(Error::Unsupported)
+    }
+
+    fn quad(&self) -> bool {
+        self.qspi.lanes() == QspiMode::QuadChannel
+    }
+
+    fn status(&mut self) -> Result<Status, Error<Q::Error>> {
         let mut sr_arr = [1u8; 1];
-        self.qspi
-            .transfer(commands::GET_STATUS, &mut sr_arr)
-            .map_err(|_| Error::Qspi)?;
+        self.transfer(commands::GET_STATUS, &mut sr_arr)?;
 
         Ok(sr_arr[0].into())
     }
 
-    fn wait_busy(&self) -> Result<(), Error> {
-        while self.status()?.wip() {}
+    /// Read the status register until no write is in progress, failing with
+    /// [`Error::Timeout`] after `timeout` microseconds. Without a delay
+    /// source, the status register is read until the device is idle.
+    fn wait_idle(&mut self, timeout: u32) -> Result<Status, Error<Q::Error>> {
+        let mut elapsed = 0u32;
+        loop {
+            let status = self.status()?;
+            if !status.wip() {
+                return Ok(status);
+            }
+
+            if let Some(delay) = &mut self.delay {
+                if elapsed >= timeout {
+                    return Err(Error::Timeout);
+                }
+                delay.delay_us(POLL_INTERVAL_US);
+                elapsed = elapsed.saturating_add(POLL_INTERVAL_US);
+            }
+        }
+    }
+
+    /// Wait for any operation in progress, bounded by the longest operation
+    /// of the device
+    fn wait_busy(&mut self) -> Result<(), Error<Q::Error>> {
+        self.wait_idle(self.params.timeouts().chip_erase)?;
+        Ok(())
+    }
+
+    /// Wait for a register write to complete within `timeout` microseconds.
+    /// A command dropped by the device leaves the write enable latch set, in
+    /// which case the latch is cleared and [`Error::WriteProtected`]
+    /// returned.
+    fn wait_done(&mut self, timeout: u32) -> Result<(), Error<Q::Error>> {
+        let status = self.wait_idle(timeout)?;
+        self.check_completed(&status)
+    }
+
+    /// Wait for a program or erase to complete within `timeout`
+    /// microseconds, failing like [`IS25xP::wait`]
+    fn wait_operation(&mut self, timeout: u32) -> Result<(), Error<Q::Error>> {
+        let status = self.wait_idle(timeout)?;
+        self.check_operation(&status)
+    }
+
+    /// Fail with the error flagged by the device for the program or erase
+    /// that left `status`, or with [`Error::WriteProtected`] if the device
+    /// dropped it
+    fn check_operation(&mut self, status: &Status) -> Result<(), Error<Q::Error>> {
+        self.check_error_flags()?;
+        self.check_completed(status)
+    }
+
+    /// Fail with [`Error::WriteProtected`] if the write enable latch is still
+    /// set after a command completed, clearing it
+    fn check_completed(&mut self, status: &Status) -> Result<(), Error<Q::Error>> {
+        if status.wel() {
+            self.command(commands::WRITE_DISABLE)?;
+            return Err(Error::WriteProtected);
+        }
+
+        Ok(())
+    }
+
+    /// Fail with [`Error::WriteProtected`] if any address in `range` is
+    /// protected by the block protection bits of `status`
+    fn check_protection(&self, status: &Status, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        check_protection(self.tbs, &self.map, status, range)
+    }
+
+    /// Fail with [`Error::WriteProtected`] if any address in `range` is
+    /// currently protected
+    fn check_writable(&mut self, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        let status = self.status()?;
+        self.check_protection(&status, range)
+    }
+
+    /// Write the status register (WRSR) and wait for the write to complete
+    fn write_status(&mut self, value: u8) -> Result<(), Error<Q::Error>> {
+        self.check_not_suspended()?;
+        self.command(commands::WRITE_ENABLE)?;
+        self.command(commands::WRITE_STATUS.data(&[value], QspiMode::SingleChannel))?;
+        self.wait_done(self.params.timeouts().register_write)
+    }
+
+    fn function_register(&mut self) -> Result<Function, Error<Q::Error>> {
+        let mut fr = [0u8; 1];
+        self.transfer(commands::READ_FUNCTION, &mut fr)?;
+
+        Ok(fr[0].into())
+    }
+
+    /// Write the function register (WRFR) and wait for the write to complete
+    fn write_function_register(&mut self, value: u8) -> Result<(), Error<Q::Error>> {
+        self.check_not_suspended()?;
+        self.command(commands::WRITE_ENABLE)?;
+        self.command(commands::WRITE_FUNCTION.data(&[value], QspiMode::SingleChannel))?;
+        self.wait_done(self.params.timeouts().register_write)
+    }
+
+    /// Block protection level set in the status register
+    pub fn block_protection(&mut self) -> Result<BlockProtection, Error<Q::Error>> {
+        Ok(self.status()?.bp())
+    }
+
+    /// Protect blocks against program and erase, leaving the other bits of
+    /// the status register untouched
+    pub fn set_block_protection(&mut self, bp: BlockProtection) -> Result<(), Error<Q::Error>> {
+        let status = self.status()?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        self.write_status(status.with_bp(bp))
+    }
+
+    /// End of the memory array the protected blocks are counted from
+    pub fn protection_area(&mut self) -> Result<ProtectionArea, Error<Q::Error>> {
+        Ok(self.function_register()?.tbs())
+    }
+
+    /// Select the end of the memory array the protected blocks are counted
+    /// from.
+    ///
+    /// The selection is one time programmable: once the bottom area is
+    /// selected, selecting the top area again fails with [`Error::Otp`].
+    pub fn set_protection_area(&mut self, area: ProtectionArea) -> Result<(), Error<Q::Error>> {
+        self.check_is25xp()?;
+        match (self.protection_area()?, area) {
+            (current, area) if current == area => Ok(()),
+            (_, ProtectionArea::Top) => Err(Error::Otp),
+            (_, ProtectionArea::Bottom) => {
+                if self.status()?.wip() {
+                    return Err(Error::Busy);
+                }
+                // Only the TBS bit is written, as the information row lock
+                // bits are one time programmable as well
+                self.write_function_register(TBS)?;
+                self.tbs = self.tbs.map(|_| area);
+                Ok(())
+            }
+        }
+    }
+
+    /// Addresses currently protected against program and erase, or `None`
+    /// if no block is protected
+    pub fn protected_range(&mut self) -> Result<Option<Range<u32>>, Error<Q::Error>> {
+        let bp = self.block_protection()?;
+        let area = self.protection_area()?;
+        Ok(bp.range(area, &self.map))
+    }
+
+    /// Whether the Status Register Write Disable bit is set, making the
+    /// status register read-only while the WP# pin is driven low
+    pub fn status_register_locked(&mut self) -> Result<bool, Error<Q::Error>> {
+        Ok(self.status()?.srwd())
+    }
+
+    /// Make the status register, and with it the block protection and quad
+    /// enable bits, read-only by setting the Status Register Write Disable
+    /// bit and driving the WP# pin low.
+    ///
+    /// The WP# pin shares IO2, and only protects the status register while
+    /// the Quad Enable bit is clear, i.e. on single lane transports. Fails
+    /// with [`Error::Unsupported`] if the Quad Enable bit is set.
+    pub fn lock_status_register<WP: OutputPin>(
+        &mut self,
+        wp: &mut WP,
+    ) -> Result<(), Error<Q::Error>> {
+        let status = self.status()?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        if status.qe() {
+            return Err(Error::Unsupported);
+        }
+
+        if !status.srwd() {
+            self.write_status(status.bits() | SRWD)?;
+        }
+        wp.set_low().map_err(|_| Error::Pin)
+    }
+
+    /// Make the status register writable again by driving the WP# pin high
+    /// and clearing the Status Register Write Disable bit
+    pub fn unlock_status_register<WP: OutputPin>(
+        &mut self,
+        wp: &mut WP,
+    ) -> Result<(), Error<Q::Error>> {
+        wp.set_high().map_err(|_| Error::Pin)?;
+
+        let status = self.status()?;
+        if status.wip() {
+            return Err(Error::Busy);
+        }
+
+        if status.srwd() {
+            self.write_status(status.bits() & !SRWD)?;
+        }
         Ok(())
     }
 
-    pub fn read_native(&self, offset: u32, data: &mut [u8]) -> Result<(), Error> {
+    /// Read from the information row `row` (IRRD)
+    pub fn read_otp(
+        &mut self,
+        row: u8,
+        offset: u32,
+        data: &mut [u8],
+    ) -> Result<(), Error<Q::Error>> {
+        let address = self.otp_address(row, offset, data.len())?;
         self.wait_busy()?;
 
-        self.qspi
-            .transfer(
-                commands::QUAD_READ
-                    .address(offset, QspiMode::QuadChannel)
-                    .receive_length(data.len() as u32),
-                data,
-            )
-            .map_err(|_| Error::Qspi)
+        let mut cmd = commands::READ_INFORMATION_ROW;
+        cmd.address_size = self.address_mode.address_size();
+        self.transfer(
+            cmd.with_address(address).receive_length(data.len() as u32),
+            data,
+        )
+    }
+
+    /// Program the information row `row` (IRP). Fails with
+    /// [`Error::WriteProtected`] if the row is locked.
+    pub fn write_otp(&mut self, row: u8, offset: u32, data: &[u8]) -> Result<(), Error<Q::Error>> {
+        let address = self.otp_address(row, offset, data.len())?;
+        self.check_otp_unlocked(row)?;
+
+        self.command(commands::WRITE_ENABLE)?;
+        let mut cmd = commands::PROGRAM_INFORMATION_ROW;
+        cmd.address_size = self.address_mode.address_size();
+        self.command(
+            cmd.with_address(address)
+                .data(data, QspiMode::SingleChannel),
+        )?;
+        self.wait_operation(self.params.timeouts().page_program)
+    }
+
+    /// Erase the information row `row` (IRER). Fails with
+    /// [`Error::WriteProtected`] if the row is locked.
+    pub fn erase_otp(&mut self, row: u8) -> Result<(), Error<Q::Error>> {
+        let address = self.otp_address(row, 0, 0)?;
+        self.check_otp_unlocked(row)?;
+
+        self.command(commands::WRITE_ENABLE)?;
+        let mut cmd = commands::ERASE_INFORMATION_ROW;
+        cmd.address_size = self.address_mode.address_size();
+        self.command(cmd.with_address(address))?;
+        self.wait_operation(self.params.timeouts().sector_erase)
     }
 
-    pub fn write_page(&self, offset: u32, data: &[u8]) -> Result<(), Error> {
+    /// Whether the information row `row` is locked against program and erase
+    pub fn otp_locked(&mut self, row: u8) -> Result<bool, Error<Q::Error>> {
+        if row >= OTP_ROWS {
+            return Err(Error::OutOfBounds);
+        }
+
+        Ok(self.function_register()?.irl(row))
+    }
+
+    /// Permanently lock the information row `row` against program and erase.
+    ///
+    /// The lock bits are one time programmable, a locked row can never be
+    /// unlocked again.
+    pub fn lock_otp(&mut self, row: u8) -> Result<(), Error<Q::Error>> {
+        self.check_is25xp()?;
+        if self.otp_locked(row)? {
+            return Ok(());
+        }
+
         if self.status()?.wip() {
             return Err(Error::Busy);
         }
+        self.write_function_register(IRL0 << row)
+    }
 
-        if data.len() > PAGE_SIZE as usize {
-            return Err(Error::Size);
+    fn check_otp_unlocked(&mut self, row: u8) -> Result<(), Error<Q::Error>> {
+        self.check_not_suspended()?;
+        if self.status()?.wip() {
+            return Err(Error::Busy);
         }
 
-        self.qspi
-            .write(commands::WRITE_ENABLE)
-            .map_err(|_| Error::Qspi)?;
-        self.qspi
-            .write(
-                commands::QUAD_WRITE
-                    .address(offset, QspiMode::SingleChannel)
-                    .data(data, QspiMode::QuadChannel),
-            )
-            .map_err(|_| Error::Qspi)?;
+        if self.function_register()?.irl(row) {
+            return Err(Error::WriteProtected);
+        }
+        Ok(())
+    }
 
-        self.wait_busy()
+    pub fn read_native(&mut self, offset: u32, data: &mut [u8]) -> Result<(), Error<Q::Error>> {
+        self.wait_busy()?;
+        self.check_not_suspended_at(byte_range(offset, data.len())?)?;
+        self.read_array(offset, data)
     }
 
-    pub fn erase_sector(&self, sector: &Sector) -> Result<(), Error> {
-        if self.status()?.wip() {
+    /// Read the memory array with the fastest read command of the transport
+    fn read_array(&mut self, offset: u32, data: &mut [u8]) -> Result<(), Error<Q::Error>> {
+        let cmd = self.read_at(self.read_command(), offset)?;
+
+        self.transfer(cmd.receive_length(data.len() as u32), data)
+    }
+
+    pub fn write_page(&mut self, offset: u32, data: &[u8]) -> Result<(), Error<Q::Error>> {
+        self.start_write_page(offset, data)?;
+        self.wait()?;
+        if self.verify {
+            self.check_contents(offset..offset + data.len() as u32, |i| data[i])?;
+        }
+        Ok(())
+    }
+
+    /// Start programming `data` at `offset`, without waiting for the program
+    /// to complete
+    pub fn start_write_page(&mut self, offset: u32, data: &[u8]) -> Result<(), Error<Q::Error>> {
+        let status = self.status()?;
+        if status.wip() {
             return Err(Error::Busy);
         }
 
-        self.qspi
-            .write(commands::WRITE_ENABLE)
-            .map_err(|_| Error::Qspi)?;
-        self.qspi
-            .write(commands::ERASE_SECTOR.address(sector.start(), QspiMode::SingleChannel))
-            .map_err(|_| Error::Qspi)?;
-        self.wait_busy()
+        if data.len() > self.params.page_size() as usize {
+            return Err(Error::Size);
+        }
+
+        // Pages can only be programmed while an erase of another region is
+        // suspended
+        let range = byte_range(offset, data.len())?;
+        match self.suspended {
+            Some(Operation::Erase { .. }) => self.check_not_suspended_at(range.clone())?,
+            Some(Operation::Program { .. }) => return Err(Error::Suspended),
+            None => {}
+        }
+        self.check_protection(&status, range.clone())?;
+
+        let cmd = self.program_command();
+        let data_mode = cmd.data.map_or(QspiMode::SingleChannel, |(_, m)| m);
+        let cmd = self.write_at(cmd, offset)?;
+        self.command(commands::WRITE_ENABLE)?;
+        self.command(cmd.data(data, data_mode))?;
+
+        self.operation = Some(Operation::Program {
+            start: range.start,
+            end: range.end,
+        });
+        self.timeout = self.params.timeouts().page_program;
+        Ok(())
     }
 
-    pub fn erase_halfblock(&self, half_block: &HalfBlock) -> Result<(), Error> {
-        if self.status()?.wip() {
+    /// Start erasing `sector`, without waiting for the erase to complete
+    pub fn start_erase_sector(&mut self, sector: &Sector) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.sector_erase_command();
+        let timeout = self.params.timeouts().sector_erase;
+        self.start_erase(cmd, sector.start()..sector.end(), timeout)
+    }
+
+    /// Start erasing `half_block`, without waiting for the erase to complete
+    pub fn start_erase_halfblock(&mut self, half_block: &HalfBlock) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.halfblock_erase_command();
+        let timeout = self.params.timeouts().halfblock_erase;
+        self.start_erase(cmd, half_block.start()..half_block.end(), timeout)
+    }
+
+    /// Start erasing `block`, without waiting for the erase to complete
+    pub fn start_erase_block(&mut self, block: &Block) -> Result<(), Error<Q::Error>> {
+        let cmd = self.params.block_erase_command();
+        let timeout = self.params.timeouts().block_erase;
+        self.start_erase(cmd, block.start()..block.end(), timeout)
+    }
+
+    fn start_erase(
+        &mut self,
+        cmd: QspiWriteCommand<'static>,
+        range: Range<u32>,
+        timeout: u32,
+    ) -> Result<(), Error<Q::Error>> {
+        self.check_not_suspended()?;
+
+        let status = self.status()?;
+        if status.wip() {
             return Err(Error::Busy);
         }
 
-        self.qspi
-            .write(commands::WRITE_ENABLE)
-            .map_err(|_| Error::Qspi)?;
-        self.qspi
-            .write(commands::ERASE_HALF_BLOCK.address(half_block.start(), QspiMode::SingleChannel))
-            .map_err(|_| Error::Qspi)?;
-        self.wait_busy()
+        self.check_protection(&status, range.clone())?;
+
+        let cmd = self.write_at(cmd, range.start)?;
+        self.command(commands::WRITE_ENABLE)?;
+        self.command(cmd)?;
+
+        self.operation = Some(Operation::Erase {
+            start: range.start,
+            end: range.end,
+        });
+        self.timeout = timeout;
+        Ok(())
     }
 
-    pub fn erase_block(&self, block: &Block) -> Result<(), Error> {
-        if self.status()?.wip() {
+    /// Start erasing the whole memory array, without waiting for the erase to
+    /// complete. A chip erase cannot be suspended.
+    pub fn start_erase_chip(&mut self) -> Result<(), Error<Q::Error>> {
+        self.check_not_suspended()?;
+
+        let status = self.status()?;
+        if status.wip() {
             return Err(Error::Busy);
         }
 
-        self.qspi
-            .write(commands::WRITE_ENABLE)
-            .map_err(|_| Error::Qspi)?;
-        self.qspi
-            .write(commands::ERASE_BLOCK.address(block.start(), QspiMode::SingleChannel))
-            .map_err(|_| Error::Qspi)?;
-        self.wait_busy()
+        // The device ignores a chip erase while any block is protected
+        self.check_protection(&status, self.map.start()..self.map.end())?;
+
+        self.command(commands::WRITE_ENABLE)?;
+        self.command(commands::ERASE_CHIP)?;
+
+        self.timeout = self.params.timeouts().chip_erase;
+        Ok(())
+    }
+
+    /// Check whether the operation started by one of the `start_*` methods
+    /// has completed, returning [`nb::Error::WouldBlock`] while it is in
+    /// progress. Polling does not measure the duration of the operation,
+    /// which is left to the caller.
+    pub fn poll(&mut self) -> nb::Result<(), Error<Q::Error>> {
+        if self.operation.is_none() {
+            self.check_not_suspended()?;
+        }
+
+        let status = self.status()?;
+        if status.wip() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.operation = None;
+        Ok(self.check_operation(&status)?)
+    }
+
+    /// Wait for the operation started by one of the `start_*` methods to
+    /// complete. With a delay source, fails with [`Error::Timeout`] once the
+    /// maximum duration of the operation is exceeded. Fails with
+    /// [`Error::ProgramFailed`], [`Error::EraseFailed`] or
+    /// [`Error::ProtectionError`] if the device flagged the operation.
+    pub fn wait(&mut self) -> Result<(), Error<Q::Error>> {
+        if self.operation.is_none() {
+            self.check_not_suspended()?;
+        }
+
+        let status = self.wait_idle(self.timeout)?;
+        self.operation = None;
+        self.check_operation(&status)
+    }
+
+    pub fn erase_sector(&mut self, sector: &Sector) -> Result<(), Error<Q::Error>> {
+        self.start_erase_sector(sector)?;
+        self.wait()?;
+        self.check_erased(sector.start()..sector.end())
+    }
+
+    pub fn erase_halfblock(&mut self, half_block: &HalfBlock) -> Result<(), Error<Q::Error>> {
+        self.start_erase_halfblock(half_block)?;
+        self.wait()?;
+        self.check_erased(half_block.start()..half_block.end())
+    }
+
+    pub fn erase_block(&mut self, block: &Block) -> Result<(), Error<Q::Error>> {
+        self.start_erase_block(block)?;
+        self.wait()?;
+        self.check_erased(block.start()..block.end())
+    }
+
+    pub fn erase_chip(&mut self) -> Result<(), Error<Q::Error>> {
+        self.start_erase_chip()?;
+        self.wait()?;
+        self.check_erased(self.map.start()..self.map.end())
+    }
+
+    /// Fail with [`Error::VerifyFailed`] if verification is enabled and any
+    /// byte of `range` is not erased
+    fn check_erased(&mut self, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        if self.verify {
+            self.check_contents(range, |_| 0xFF)?;
+        }
+        Ok(())
+    }
+
+    /// Read back `range`, failing with [`Error::VerifyFailed`] at the first
+    /// byte differing from `expected`, which is indexed from the start of
+    /// `range`
+    fn check_contents(
+        &mut self,
+        range: Range<u32>,
+        expected: impl Fn(usize) -> u8,
+    ) -> Result<(), Error<Q::Error>> {
+        match self.find_mismatch(range, expected)? {
+            Some(address) => Err(Error::VerifyFailed { address }),
+            None => Ok(()),
+        }
+    }
+
+    /// Address of the first byte of `range` differing from `expected`, which
+    /// is indexed from the start of `range`
+    fn find_mismatch(
+        &mut self,
+        range: Range<u32>,
+        expected: impl Fn(usize) -> u8,
+    ) -> Result<Option<u32>, Error<Q::Error>> {
+        let start = range.start;
+        let mut buf = [0u8; READ_BACK_CHUNK_SIZE];
+        self.scan(range, &mut buf, |address, chunk| {
+            let offset = (address - start) as usize;
+            (0..chunk.len())
+                .find(|&i| chunk[i] != expected(offset + i))
+                .map(|i| address + i as u32)
+        })
+    }
+
+    /// Read `range` in chunks the size of `buf`, passing each chunk along
+    /// with its address to `f` until it returns a value
+    fn scan<T>(
+        &mut self,
+        range: Range<u32>,
+        buf: &mut [u8],
+        mut f: impl FnMut(u32, &[u8]) -> Option<T>,
+    ) -> Result<Option<T>, Error<Q::Error>> {
+        self.wait_busy()?;
+        self.check_not_suspended_at(range.clone())?;
+
+        for address in range.clone().step_by(buf.len()) {
+            let len = (range.end - address).min(buf.len() as u32) as usize;
+            let chunk = &mut buf[..len];
+            self.read_array(address, chunk)?;
+
+            if let Some(value) = f(address, chunk) {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Compute `checksum` over `range`, reading the memory array in bursts
+    /// through a buffer on the stack. Any [`Checksum`] implementation can be
+    /// used, such as the software [`Crc32`] and [`Crc16`] or a wrapper around
+    /// a hardware CRC unit.
+    pub fn checksum<C: Checksum>(
+        &mut self,
+        range: Range<u32>,
+        mut checksum: C,
+    ) -> Result<C::Output, Error<Q::Error>> {
+        if range.start > range.end || range.end > self.map.end() {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut buf = [0u8; CHECKSUM_BURST_SIZE];
+        self.scan(range, &mut buf, |_, chunk| {
+            checksum.update(chunk);
+            None::<()>
+        })?;
+        Ok(checksum.finish())
+    }
+
+    /// Whether every byte of `range` is erased
+    pub fn is_blank(&mut self, range: Range<u32>) -> Result<bool, Error<Q::Error>> {
+        Ok(self.first_non_blank(range)?.is_none())
+    }
+
+    /// Address of the first byte of `range` that is not erased, or `None` if
+    /// the whole range is blank
+    pub fn first_non_blank(&mut self, range: Range<u32>) -> Result<Option<u32>, Error<Q::Error>> {
+        if range.start > range.end || range.end > self.map.end() {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.find_mismatch(range, |_| 0xFF)
+    }
+
+    /// Erase the sectors from `from` to `to` that are not already blank,
+    /// with the same alignment requirements as [`NorFlash::erase`]. Returns
+    /// the number of sectors erased.
+    pub fn erase_non_blank(&mut self, from: u32, to: u32) -> Result<u32, Error<Q::Error>> {
+        self.map.check_erase_range(from, to)?;
+        self.check_writable(from..to)?;
+
+        let sector_size = self.map.sector_size;
+        let mut erased = 0;
+        for address in (from..to).step_by(sector_size as usize) {
+            let sector = self.map.sector_at(address).ok_or(Error::OutOfBounds)?;
+            if !self.is_blank(sector.start()..sector.end())? {
+                self.erase_sector(&sector)?;
+                erased += 1;
+            }
+        }
+
+        Ok(erased)
     }
 
-    pub fn erase_chip(&self) -> Result<(), Error> {
+    /// Suspend the operation started by one of the `start_*` methods
+    /// (PERSUS), allowing the rest of the memory array to be read, and pages
+    /// outside of a suspended erase to be programmed.
+    ///
+    /// Returns the suspended operation, or `None` if there was no operation
+    /// left to suspend.
+    pub fn suspend(&mut self) -> Result<Option<Operation>, Error<Q::Error>> {
+        self.check_is25xp()?;
+        if self.suspended.is_some() || self.operation.is_none() {
+            return Ok(self.suspended);
+        }
+
+        self.command(commands::SUSPEND)?;
+        self.wait_busy()?;
+
+        let function = self.function_register()?;
+        if function.esus() || function.psus() {
+            self.suspended = self.operation.take();
+            self.suspended_timeout = self.timeout;
+        } else {
+            // Completed before the suspend took effect
+            self.operation = None;
+        }
+        Ok(self.suspended)
+    }
+
+    /// Resume the suspended operation (PERRSM). Its completion can be
+    /// awaited with [`IS25xP::poll`] or [`IS25xP::wait`].
+    pub fn resume(&mut self) -> Result<(), Error<Q::Error>> {
+        if self.suspended.is_none() {
+            return Ok(());
+        }
+
+        // A program issued during the suspend has to complete first
         if self.status()?.wip() {
             return Err(Error::Busy);
         }
 
-        self.qspi
-            .write(commands::WRITE_ENABLE)
-            .map_err(|_| Error::Qspi)?;
-        self.qspi
-            .write(commands::ERASE_CHIP)
-            .map_err(|_| Error::Qspi)?;
-        self.wait_busy()
+        self.command(commands::RESUME)?;
+        self.operation = self.suspended.take();
+        self.timeout = self.suspended_timeout;
+        Ok(())
+    }
+
+    /// Operation currently suspended by [`IS25xP::suspend`]
+    pub fn suspended(&self) -> Option<Operation> {
+        self.suspended
+    }
+
+    /// Fail with [`Error::Unsupported`] on devices other than the IS25LP and
+    /// IS25WP, which lay out the function and extended read registers
+    /// differently, if they have them at all
+    fn check_is25xp(&self) -> Result<(), Error<Q::Error>> {
+        if !self.id.is_is25xp() {
+            return Err(Error::Unsupported);
+        }
+        Ok(())
+    }
+
+    /// Fail with [`Error::Suspended`] while an operation is suspended
+    fn check_not_suspended(&self) -> Result<(), Error<Q::Error>> {
+        match self.suspended {
+            Some(_) => Err(Error::Suspended),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail with [`Error::Suspended`] if any address in `range` is affected by
+    /// the suspended operation, as its contents are undefined until resumed
+    fn check_not_suspended_at(&self, range: Range<u32>) -> Result<(), Error<Q::Error>> {
+        match self.suspended {
+            Some(op) if overlaps(&op.range(), &range) => Err(Error::Suspended),
+            _ => Ok(()),
+        }
     }
 }
 
-pub struct MemoryMap;
-pub struct Block(u32);
-pub struct HalfBlock(u32);
-pub struct Sector(u32);
-pub struct Page(u32);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryMap {
+    page_size: u32,
+    sector_size: u32,
+    halfblock_size: u32,
+    block_size: u32,
+    capacity: u32,
+}
+#[derive(Debug, Clone, Copy)]
+pub struct Block(u32, MemoryMap);
+#[derive(Debug, Clone, Copy)]
+pub struct HalfBlock(u32, MemoryMap);
+#[derive(Debug, Clone, Copy)]
+pub struct Sector(u32, MemoryMap);
+#[derive(Debug, Clone, Copy)]
+pub struct Page(u32, MemoryMap);
 
 const BASE_ADDRESS: u32 = 0x0000_0000;
-const PAGES_PER_SECTOR: u32 = 16;
-const SECTORS_PER_BLOCK: u32 = 16;
-const SECTORS_PER_HALFBLOCK: u32 = 8;
-const HALFBLOCKS_PER_BLOCK: u32 = SECTORS_PER_BLOCK / SECTORS_PER_HALFBLOCK;
-const PAGES_PER_BLOCK: u32 = PAGES_PER_SECTOR * SECTORS_PER_BLOCK;
-const PAGES_PER_HALFBLOCK: u32 = PAGES_PER_SECTOR * HALFBLOCKS_PER_BLOCK;
-const PAGE_SIZE: u32 = 256;
-const SECTOR_SIZE: u32 = PAGE_SIZE * PAGES_PER_SECTOR;
-const HALFBLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_HALFBLOCK;
-const BLOCK_SIZE: u32 = SECTOR_SIZE * SECTORS_PER_BLOCK;
-const MEMORY_SIZE: u32 = NUMBER_OF_BLOCKS * BLOCK_SIZE;
-const NUMBER_OF_BLOCKS: u32 = 256;
-const NUMBER_OF_HALFBLOCKS: u32 = NUMBER_OF_BLOCKS * HALFBLOCKS_PER_BLOCK;
-const NUMBER_OF_SECTORS: u32 = NUMBER_OF_BLOCKS * SECTORS_PER_BLOCK;
-const NUMBER_OF_PAGES: u32 = NUMBER_OF_SECTORS * PAGES_PER_SECTOR;
 
 impl MemoryMap {
-    pub fn blocks() -> impl Iterator<Item = Block> {
-        (0..NUMBER_OF_BLOCKS).map(Block)
+    pub fn new<P: FlashParams>(params: &P) -> Self {
+        Self {
+            page_size: params.page_size(),
+            sector_size: params.sector_size(),
+            halfblock_size: params.halfblock_size(),
+            block_size: params.block_size(),
+            capacity: params.capacity(),
+        }
     }
-    pub fn halfblocks() -> impl Iterator<Item = HalfBlock> {
-        (0..NUMBER_OF_HALFBLOCKS).map(HalfBlock)
+
+    /// Split `start..end` into consecutive regions of `size` bytes
+    fn regions<R>(
+        &self,
+        start: u32,
+        end: u32,
+        size: u32,
+        region: fn(u32, MemoryMap) -> R,
+    ) -> impl Iterator<Item = R> {
+        let map = *self;
+        (start / size..end / size).map(move |i| region(i, map))
     }
-    pub fn sectors() -> impl Iterator<Item = Sector> {
-        (0..NUMBER_OF_SECTORS).map(Sector)
+
+    pub fn blocks(&self) -> impl Iterator<Item = Block> {
+        self.regions(self.start(), self.end(), self.block_size, Block)
     }
-    pub fn pages() -> impl Iterator<Item = Page> {
-        (0..NUMBER_OF_PAGES).map(Page)
+    pub fn halfblocks(&self) -> impl Iterator<Item = HalfBlock> {
+        self.regions(self.start(), self.end(), self.halfblock_size, HalfBlock)
     }
-    pub const fn start() -> u32 {
+    pub fn sectors(&self) -> impl Iterator<Item = Sector> {
+        self.regions(self.start(), self.end(), self.sector_size, Sector)
+    }
+    pub fn pages(&self) -> impl Iterator<Item = Page> {
+        self.regions(self.start(), self.end(), self.page_size, Page)
+    }
+    pub fn block_at(&self, address: u32) -> Option<Block> {
+        self.contains(address)
+            .then(|| Block((address - BASE_ADDRESS) / self.block_size, *self))
+    }
+    pub fn halfblock_at(&self, address: u32) -> Option<HalfBlock> {
+        self.contains(address)
+            .then(|| HalfBlock((address - BASE_ADDRESS) / self.halfblock_size, *self))
+    }
+    pub fn sector_at(&self, address: u32) -> Option<Sector> {
+        self.contains(address)
+            .then(|| Sector((address - BASE_ADDRESS) / self.sector_size, *self))
+    }
+    pub fn page_at(&self, address: u32) -> Option<Page> {
+        self.contains(address)
+            .then(|| Page((address - BASE_ADDRESS) / self.page_size, *self))
+    }
+    pub const fn start(&self) -> u32 {
         BASE_ADDRESS
     }
-    pub const fn end() -> u32 {
-        BASE_ADDRESS + MEMORY_SIZE as u32
+    pub const fn end(&self) -> u32 {
+        BASE_ADDRESS + self.capacity
+    }
+    pub const fn size(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Addresses of `len` bytes from `offset`, failing with
+    /// [`Error::OutOfBounds`] past the end of the memory array
+    fn checked_range<E>(&self, offset: u32, len: usize) -> Result<Range<u32>, Error<E>> {
+        let range = byte_range(offset, len)?;
+        if range.end > self.end() {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(range)
+    }
+
+    /// Check that `from..to` can be erased, failing with [`Error::Alignment`]
+    /// unless both ends are sector aligned, and with [`Error::OutOfBounds`]
+    /// if the range is reversed or past the end of the memory array
+    fn check_erase_range<E>(&self, from: u32, to: u32) -> Result<(), Error<E>> {
+        let sector_size = self.sector_size;
+        if !to.is_multiple_of(sector_size) || !from.is_multiple_of(sector_size) {
+            return Err(Error::Alignment);
+        }
+
+        if from > to || to > self.end() {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    /// Largest region starting at `from` that fits before `to`, with `from`
+    /// sector aligned
+    fn erase_region_at<E>(&self, from: u32, to: u32) -> Result<EraseRegion, Error<E>> {
+        if self.start() == from && self.end() == to {
+            return Ok(EraseRegion::Chip);
+        }
+
+        let fits = |size: u32| from.is_multiple_of(size) && from + size <= to;
+        let region = if fits(self.block_size) {
+            self.block_at(from).map(EraseRegion::Block)
+        } else if fits(self.halfblock_size) {
+            self.halfblock_at(from).map(EraseRegion::HalfBlock)
+        } else {
+            self.sector_at(from).map(EraseRegion::Sector)
+        };
+        region.ok_or(Error::OutOfBounds)
     }
-    pub const fn size() -> usize {
-        MEMORY_SIZE as usize
+
+    /// Split `bytes` written from `address` at the page boundaries
+    fn page_chunks<'a>(
+        &self,
+        address: u32,
+        bytes: &'a [u8],
+    ) -> impl Iterator<Item = (u32, &'a [u8])> {
+        let page_size = self.page_size;
+        let mut address = address;
+        let mut bytes = bytes;
+        core::iter::from_fn(move || {
+            if bytes.is_empty() {
+                return None;
+            }
+            let len = ((page_size - address % page_size) as usize).min(bytes.len());
+            let (page, rest) = bytes.split_at(len);
+            let chunk = (address, page);
+            address += len as u32;
+            bytes = rest;
+            Some(chunk)
+        })
+    }
+}
+
+impl Region for MemoryMap {
+    fn contains(&self, address: u32) -> bool {
+        (self.start() <= address) && (address < self.end())
     }
 }
 
 impl Block {
     pub fn sectors(&self) -> impl Iterator<Item = Sector> {
-        ((self.0 * SECTORS_PER_BLOCK)..((1 + self.0) * SECTORS_PER_BLOCK)).map(Sector)
+        self.1
+            .regions(self.start(), self.end(), self.1.sector_size, Sector)
     }
     pub fn halfblocks(&self) -> impl Iterator<Item = HalfBlock> {
-        ((self.0 * HALFBLOCKS_PER_BLOCK)..((1 + self.0) * HALFBLOCKS_PER_BLOCK)).map(HalfBlock)
+        self.1
+            .regions(self.start(), self.end(), self.1.halfblock_size, HalfBlock)
     }
     pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_BLOCK)..((1 + self.0) * PAGES_PER_BLOCK)).map(Page)
+        self.1
+            .regions(self.start(), self.end(), self.1.page_size, Page)
     }
     pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+        BASE_ADDRESS + self.0 * self.size() as u32
     }
     pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
-    }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::blocks().find(|s| s.contains(address))
+        self.start() + self.size() as u32
     }
-    pub const fn size() -> usize {
-        BLOCK_SIZE as usize
+    pub const fn size(&self) -> usize {
+        self.1.block_size as usize
     }
 }
 
 impl HalfBlock {
     pub fn sectors(&self) -> impl Iterator<Item = Sector> {
-        ((self.0 * SECTORS_PER_HALFBLOCK)..((1 + self.0) * SECTORS_PER_HALFBLOCK)).map(Sector)
+        self.1
+            .regions(self.start(), self.end(), self.1.sector_size, Sector)
     }
     pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_HALFBLOCK)..((1 + self.0) * PAGES_PER_HALFBLOCK)).map(Page)
+        self.1
+            .regions(self.start(), self.end(), self.1.page_size, Page)
     }
     pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+        BASE_ADDRESS + self.0 * self.size() as u32
     }
     pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
-    }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::halfblocks().find(|s| s.contains(address))
+        self.start() + self.size() as u32
     }
-    pub const fn size() -> usize {
-        HALFBLOCK_SIZE as usize
+    pub const fn size(&self) -> usize {
+        self.1.halfblock_size as usize
     }
 }
 
 impl Sector {
     pub fn pages(&self) -> impl Iterator<Item = Page> {
-        ((self.0 * PAGES_PER_SECTOR)..((1 + self.0) * PAGES_PER_SECTOR)).map(Page)
+        self.1
+            .regions(self.start(), self.end(), self.1.page_size, Page)
     }
     pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+        BASE_ADDRESS + self.0 * self.size() as u32
     }
     pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
+        self.start() + self.size() as u32
     }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::sectors().find(|s| s.contains(address))
-    }
-    pub const fn size() -> usize {
-        SECTOR_SIZE as usize
+    pub const fn size(&self) -> usize {
+        self.1.sector_size as usize
     }
 }
 
 impl Page {
     pub fn start(&self) -> u32 {
-        BASE_ADDRESS + self.0 * Self::size() as u32
+        BASE_ADDRESS + self.0 * self.size() as u32
     }
     pub fn end(&self) -> u32 {
-        self.start() + Self::size() as u32
-    }
-    pub fn at(address: u32) -> Option<Self> {
-        MemoryMap::pages().find(|s| s.contains(address))
+        self.start() + self.size() as u32
     }
-    pub const fn size() -> usize {
-        PAGE_SIZE as usize
+    pub const fn size(&self) -> usize {
+        self.1.page_size as usize
     }
 }
 
 impl Region for Block {
     fn contains(&self, address: u32) -> bool {
-        let start = BLOCK_SIZE * self.0;
-        (start <= address) && (address < start + BLOCK_SIZE)
+        (self.start() <= address) && (address < self.end())
     }
 }
 
 impl Region for HalfBlock {
     fn contains(&self, address: u32) -> bool {
-        let start = HALFBLOCK_SIZE * self.0;
-        (start <= address) && (address < start + HALFBLOCK_SIZE)
+        (self.start() <= address) && (address < self.end())
     }
 }
 
 impl Region for Sector {
     fn contains(&self, address: u32) -> bool {
-        let start = SECTOR_SIZE * self.0;
-        (start <= address) && (address < start + SECTOR_SIZE)
+        (self.start() <= address) && (address < self.end())
     }
 }
 
 impl Region for Page {
     fn contains(&self, address: u32) -> bool {
-        let start = PAGE_SIZE * self.0;
-        (start <= address) && (address < start + PAGE_SIZE)
+        (self.start() <= address) && (address < self.end())
     }
 }
 
-impl<Q: Qspi> ReadNorFlash for IS25xP<Q> {
-    type Error = Error;
+impl<E: fmt::Debug> NorFlashError for Error<E> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Error::Alignment => NorFlashErrorKind::NotAligned,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
 
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Busy => f.write_str("device busy with a program or erase"),
+            Error::Qspi(e) => write!(f, "transport error: {:?}", e),
+            Error::OutOfBounds => f.write_str("address out of bounds"),
+            Error::Alignment => f.write_str("address not aligned to an erasable region"),
+            Error::Size => f.write_str("data larger than a page"),
+            Error::UnknownDevice(id) => write!(
+                f,
+                "unknown device {:02X} {:02X} {:02X}",
+                id.manufacturer(),
+                id.memory_type(),
+                id.capacity()
+            ),
+            Error::Sfdp => f.write_str("no usable SFDP basic flash parameter table"),
+            Error::Otp => f.write_str("one time programmable bit already programmed"),
+            Error::Pin => f.write_str("failed to drive the WP# pin"),
+            Error::Suspended => f.write_str("not allowed while an operation is suspended"),
+            Error::WriteProtected => f.write_str("region write protected or command dropped"),
+            Error::Timeout => f.write_str("operation timed out"),
+            Error::Unsupported => f.write_str("not supported by the transport or configuration"),
+            Error::VerifyFailed { address } => {
+                write!(f, "verification failed at address {:#010X}", address)
+            }
+            Error::ProgramFailed => f.write_str("program failed"),
+            Error::EraseFailed => f.write_str("erase failed"),
+            Error::ProtectionError => f.write_str("program or erase of a protected region"),
+        }
+    }
+}
+
+impl<Q: Qspi, P: FlashParams, D: DelayNs> ErrorType for IS25xP<Q, P, D> {
+    type Error = Error<Q::Error>;
+}
+
+impl<Q: Qspi, P: FlashParams, D: DelayNs> ReadNorFlash for IS25xP<Q, P, D> {
     const READ_SIZE: usize = 1;
 
     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        if offset > self.capacity() as u32 {
-            return Err(Error::OutOfBounds);
-        }
-
+        self.map.checked_range::<Q::Error>(offset, bytes.len())?;
         self.read_native(offset, bytes)
     }
 
     fn capacity(&self) -> usize {
-        MemoryMap::size()
+        self.map.size()
     }
 }
 
-impl<Q: Qspi> NorFlash for IS25xP<Q> {
+impl<Q: Qspi, P: FlashParams, D: DelayNs> NorFlash for IS25xP<Q, P, D> {
     const WRITE_SIZE: usize = 1;
 
+    /// Note: Devices described by a [`FlashParams`] with a sector size larger
+    /// than 4 KiB additionally require erases to be aligned to their sector
+    /// size.
     const ERASE_SIZE: usize = SECTOR_SIZE as usize;
 
     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        if offset as usize + bytes.len() > self.capacity() {
-            return Err(Error::OutOfBounds);
-        }
-
-        let mut alignment_offset = 0;
-        let mut aligned_address = MemoryMap::start() + offset;
-
-        if offset % PAGE_SIZE != 0 {
-            alignment_offset = core::cmp::min(PAGE_SIZE - offset % PAGE_SIZE, bytes.len() as u32);
-            self.write_page(aligned_address, &bytes[..alignment_offset as usize])?;
-
-            aligned_address += alignment_offset;
-        }
-
-        let mut chunks = bytes[alignment_offset as usize..].chunks_exact(PAGE_SIZE as usize);
-        for exact_chunk in &mut chunks {
-            self.write_page(aligned_address, exact_chunk)?;
-            aligned_address += PAGE_SIZE;
-        }
+        let range = self.map.checked_range(offset, bytes.len())?;
+        self.check_writable(range)?;
 
-        let remainder = chunks.remainder();
-        if !remainder.is_empty() {
-            self.write_page(aligned_address, remainder)?;
+        for (address, page) in self.map.page_chunks(self.map.start() + offset, bytes) {
+            self.write_page(address, page)?;
         }
 
         Ok(())
     }
 
     fn erase(&mut self, mut from: u32, to: u32) -> Result<(), Self::Error> {
-        // Check that from & to is properly aligned to a proper erase resolution
-        if to % Self::ERASE_SIZE as u32 != 0 || from % Self::ERASE_SIZE as u32 != 0 {
-            return Err(Error::Alignment);
-        }
-
-        // Shortcut to erase entire chip
-        if MemoryMap::start() == from && MemoryMap::end() == to {
-            return self.erase_chip();
-        }
+        self.map.check_erase_range(from, to)?;
+        self.check_writable(from..to)?;
 
         while from < to {
-            if from % BLOCK_SIZE == 0 && from + BLOCK_SIZE <= to {
-                let block = Block::at(from).ok_or(Error::OutOfBounds)?;
-                self.erase_block(&block)?;
-                from += BLOCK_SIZE;
-            } else if from % HALFBLOCK_SIZE == 0 && from + HALFBLOCK_SIZE <= to {
-                let halfblock = HalfBlock::at(from).ok_or(Error::OutOfBounds)?;
-                self.erase_halfblock(&halfblock)?;
-                from += HALFBLOCK_SIZE;
-            } else {
-                let sector = Sector::at(from).ok_or(Error::OutOfBounds)?;
-                self.erase_sector(&sector)?;
-                from += SECTOR_SIZE;
-            }
+            from = match self.map.erase_region_at(from, to)? {
+                EraseRegion::Chip => {
+                    self.erase_chip()?;
+                    to
+                }
+                EraseRegion::Block(block) => {
+                    self.erase_block(&block)?;
+                    block.end()
+                }
+                EraseRegion::HalfBlock(half_block) => {
+                    self.erase_halfblock(&half_block)?;
+                    half_block.end()
+                }
+                EraseRegion::Sector(sector) => {
+                    self.erase_sector(&sector)?;
+                    sector.end()
+                }
+            };
         }
 
         Ok(())
@@ -431,4 +1941,4 @@ impl<Q: Qspi> NorFlash for IS25xP<Q> {
 /// Note: A program operation can alter “1”s into “0”s. The same byte location
 /// or page may be programmed more than once, to incrementally change “1”s to
 /// “0”s. An erase operation is required to change “0”s to “1”s.
-impl<Q: Qspi> MultiwriteNorFlash for IS25xP<Q> {}
+impl<Q: Qspi, P: FlashParams, D: DelayNs> MultiwriteNorFlash for IS25xP<Q, P, D> {}
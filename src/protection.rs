@@ -0,0 +1,107 @@
+//! Block protection of the memory array
+//!
+//! The BP3-BP0 bits of the status register select how many blocks are
+//! protected against program and erase, and the TBS bit of the function
+//! register whether those blocks are counted from the top or the bottom of
+//! the memory array.
+
+use core::ops::Range;
+
+use crate::MemoryMap;
+
+/// Level of block protection, as the value of the BP3-BP0 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockProtection(u8);
+
+impl BlockProtection {
+    /// No block is protected
+    pub const NONE: Self = Self(0);
+
+    /// The whole memory array is protected
+    pub const ALL: Self = Self(0xF);
+
+    /// Protection level from the value of the BP3-BP0 bits, or `None` if
+    /// `bits` does not fit in 4 bits
+    pub const fn new(bits: u8) -> Option<Self> {
+        if bits <= 0xF {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Smallest protection level covering at least `blocks` blocks
+    pub fn covering(blocks: u32) -> Self {
+        match blocks {
+            0 => Self::NONE,
+            n => {
+                let log2 = n
+                    .checked_next_power_of_two()
+                    .map_or(32, u32::trailing_zeros);
+                Self((log2 as u8 + 1).min(0xF))
+            }
+        }
+    }
+
+    /// Value of the BP3-BP0 bits
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Number of blocks protected on a device with `total` blocks. Each level
+    /// doubles the number of protected blocks, until the whole memory array
+    /// is protected (See Tables 6.4 for details).
+    pub fn blocks(&self, total: u32) -> u32 {
+        match self.0 {
+            0 => 0,
+            n => (1u32 << (n - 1)).min(total),
+        }
+    }
+
+    /// Addresses protected within `map`, or `None` if no block is protected
+    pub fn range(&self, area: ProtectionArea, map: &MemoryMap) -> Option<Range<u32>> {
+        let size = self.blocks(map.capacity / map.block_size) * map.block_size;
+        match (size, area) {
+            (0, _) => None,
+            (size, ProtectionArea::Top) => Some(map.capacity - size..map.capacity),
+            (size, ProtectionArea::Bottom) => Some(0..size),
+        }
+    }
+}
+
+/// End of the memory array the protected blocks are counted from, as selected
+/// by the TBS bit of the function register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectionArea {
+    /// Protected blocks end at the highest address (default)
+    Top,
+    /// Protected blocks start at address 0
+    Bottom,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        assert_eq!(BlockProtection::NONE.blocks(256), 0);
+        assert_eq!(BlockProtection::new(1).unwrap().blocks(256), 1);
+        assert_eq!(BlockProtection::new(4).unwrap().blocks(256), 8);
+        assert_eq!(BlockProtection::new(8).unwrap().blocks(256), 128);
+        assert_eq!(BlockProtection::new(9).unwrap().blocks(256), 256);
+        assert_eq!(BlockProtection::ALL.blocks(256), 256);
+        assert_eq!(BlockProtection::new(6).unwrap().blocks(32), 32);
+        assert_eq!(BlockProtection::new(0x10), None);
+    }
+
+    #[test]
+    fn covering() {
+        assert_eq!(BlockProtection::covering(0), BlockProtection::NONE);
+        assert_eq!(BlockProtection::covering(1).bits(), 1);
+        assert_eq!(BlockProtection::covering(3).bits(), 3);
+        assert_eq!(BlockProtection::covering(4).bits(), 3);
+        assert_eq!(BlockProtection::covering(256).bits(), 9);
+        assert_eq!(BlockProtection::covering(u32::MAX), BlockProtection::ALL);
+    }
+}
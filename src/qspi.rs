@@ -0,0 +1,124 @@
+//! Transport independent description of the transactions issued by the driver.
+//!
+//! The driver only ever builds [`QspiWriteCommand`]s and [`QspiReadCommand`]s,
+//! and leaves it to the [`Qspi`] implementation to translate them into
+//! whatever the underlying peripheral expects.
+
+/// Number of data lanes used by a phase of a transaction, ordered by width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QspiMode {
+    SingleChannel,
+    DualChannel,
+    QuadChannel,
+}
+
+/// Width of the address phase of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSize {
+    Addr24Bit,
+    Addr32Bit,
+}
+
+impl AddressSize {
+    /// Number of bytes clocked out during the address phase
+    pub const fn bytes(&self) -> usize {
+        match self {
+            AddressSize::Addr24Bit => 3,
+            AddressSize::Addr32Bit => 4,
+        }
+    }
+}
+
+/// A transaction sending zero or more bytes to the flash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QspiWriteCommand<'a> {
+    pub instruction: Option<(u8, QspiMode)>,
+    pub address: Option<(u32, QspiMode)>,
+    pub address_size: AddressSize,
+    pub dummy_cycles: u8,
+    pub data: Option<(&'a [u8], QspiMode)>,
+    pub double_data_rate: bool,
+}
+
+impl<'a> QspiWriteCommand<'a> {
+    pub const fn instruction(mut self, instruction: u8, mode: QspiMode) -> Self {
+        self.instruction = Some((instruction, mode));
+        self
+    }
+
+    /// Set the address, keeping the lane count of the address phase
+    pub const fn with_address(mut self, addr: u32) -> Self {
+        if let Some((_, mode)) = self.address {
+            self.address = Some((addr, mode));
+        }
+        self
+    }
+
+    pub const fn address(mut self, addr: u32, mode: QspiMode) -> Self {
+        self.address = Some((addr, mode));
+        self
+    }
+
+    pub const fn data(mut self, data: &'a [u8], mode: QspiMode) -> Self {
+        self.data = Some((data, mode));
+        self
+    }
+}
+
+/// A transaction receiving bytes from the flash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QspiReadCommand {
+    pub instruction: Option<(u8, QspiMode)>,
+    pub address: Option<(u32, QspiMode)>,
+    pub address_size: AddressSize,
+    pub dummy_cycles: u8,
+    pub data_mode: QspiMode,
+    pub receive_length: u32,
+    /// Transfer the address, dummy and data phases on both clock edges,
+    /// while the instruction phase stays single rate
+    pub double_data_rate: bool,
+}
+
+impl QspiReadCommand {
+    pub const fn instruction(mut self, instruction: u8, mode: QspiMode) -> Self {
+        self.instruction = Some((instruction, mode));
+        self
+    }
+
+    /// Set the address, keeping the lane count of the address phase
+    pub const fn with_address(mut self, addr: u32) -> Self {
+        if let Some((_, mode)) = self.address {
+            self.address = Some((addr, mode));
+        }
+        self
+    }
+
+    pub const fn address(mut self, addr: u32, mode: QspiMode) -> Self {
+        self.address = Some((addr, mode));
+        self
+    }
+
+    pub const fn receive_length(mut self, length: u32) -> Self {
+        self.receive_length = length;
+        self
+    }
+}
+
+pub trait Qspi {
+    type Error: core::fmt::Debug;
+
+    fn write(&mut self, cmd: QspiWriteCommand) -> Result<(), Self::Error>;
+    fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Widest data phase supported by the transport. The driver falls back to
+    /// the single lane command set unless this is [`QspiMode::QuadChannel`].
+    fn lanes(&self) -> QspiMode {
+        QspiMode::QuadChannel
+    }
+
+    /// Whether the transport can carry out commands with
+    /// `double_data_rate` set. The DTR read modes are refused otherwise.
+    fn double_data_rate(&self) -> bool {
+        false
+    }
+}
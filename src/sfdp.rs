@@ -0,0 +1,333 @@
+//! Serial Flash Discoverable Parameters (JESD216)
+//!
+//! Only the basic flash parameter table is interpreted, which describes the
+//! density, the erase types, the fast read modes and the addressing of the
+//! device.
+
+use crate::flash_params::FourByteEntry;
+
+/// "SFDP" in little endian
+pub const SIGNATURE: u32 = 0x5044_4653;
+
+/// Parameter ID of the basic flash parameter table
+pub const BASIC_FLASH_PARAMETERS: u16 = 0xFF00;
+
+/// Number of DWORDs of the basic flash parameter table interpreted by
+/// [`BasicFlashParameters::parse`]
+pub const BASIC_FLASH_PARAMETERS_LEN: usize = 16;
+
+/// Number of parameter headers following the SFDP header, or `None` if the
+/// signature does not match
+pub fn parse_header(buf: &[u8; 8]) -> Option<usize> {
+    if u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) != SIGNATURE {
+        return None;
+    }
+
+    Some(buf[6] as usize + 1)
+}
+
+/// Location of a parameter table, as found in its parameter header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParameterHeader {
+    pub id: u16,
+    /// Length of the table in DWORDs
+    pub length: usize,
+    /// Address of the table in the SFDP space
+    pub pointer: u32,
+}
+
+impl From<[u8; 8]> for ParameterHeader {
+    fn from(v: [u8; 8]) -> Self {
+        Self {
+            id: u16::from_le_bytes([v[0], v[7]]),
+            length: v[3] as usize,
+            pointer: u32::from_le_bytes([v[4], v[5], v[6], 0]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastRead {
+    pub opcode: u8,
+    /// Wait states and mode clocks combined
+    pub dummy_cycles: u8,
+}
+
+impl FastRead {
+    /// Decode a 16 bit fast read descriptor
+    fn parse(v: u32) -> Self {
+        Self {
+            opcode: (v >> 8) as u8,
+            dummy_cycles: (v & 0x1F) as u8 + ((v >> 5) & 0x7) as u8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EraseType {
+    /// Size of the erased region in bytes
+    pub size: u32,
+    pub opcode: u8,
+}
+
+impl EraseType {
+    /// Decode a 16 bit erase type descriptor
+    fn parse(v: u32) -> Option<Self> {
+        match v & 0xFF {
+            0 => None,
+            n if n < 32 => Some(Self {
+                size: 1 << n,
+                opcode: (v >> 8) as u8,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressBytes {
+    Three,
+    ThreeOrFour,
+    Four,
+}
+
+/// Location of the Quad Enable bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuadEnable {
+    /// The device has no Quad Enable bit
+    NotRequired,
+    /// Bit 6 of the status register, as on the IS25LP/IS25WP parts
+    Status6,
+    /// Any other location, or not described by the table
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicFlashParameters {
+    /// Size of the memory array in bytes
+    pub capacity: u32,
+    /// Size of a program page in bytes
+    pub page_size: u32,
+    pub erase_types: [Option<EraseType>; 4],
+    pub address_bytes: AddressBytes,
+    pub read_1_1_2: Option<FastRead>,
+    pub read_1_2_2: Option<FastRead>,
+    pub read_1_1_4: Option<FastRead>,
+    pub read_1_4_4: Option<FastRead>,
+    pub quad_enable: QuadEnable,
+    /// How the device is switched to 4 byte addresses, or `None` if the table
+    /// describes no supported method
+    pub four_byte_entry: Option<FourByteEntry>,
+}
+
+impl BasicFlashParameters {
+    /// Interpret the DWORDs of a basic flash parameter table. Tables of the
+    /// original JESD216 revision only contain 9 DWORDs, in which case the
+    /// page size defaults to 256 bytes.
+    pub fn parse(dwords: &[u32]) -> Option<Self> {
+        if dwords.len() < 9 {
+            return None;
+        }
+
+        let capacity = if dwords[1] & 0x8000_0000 == 0 {
+            (dwords[1] as u64 + 1) / 8
+        } else {
+            match dwords[1] & 0x7FFF_FFFF {
+                n if n < 64 => (1u64 << n) / 8,
+                _ => return None,
+            }
+        };
+        if capacity == 0 || capacity > u32::MAX as u64 + 1 {
+            return None;
+        }
+
+        let page_size = match dwords.get(10) {
+            Some(dw) => 1 << ((dw >> 4) & 0xF),
+            None => 256,
+        };
+
+        let address_bytes = match (dwords[0] >> 17) & 0x3 {
+            0b00 => AddressBytes::Three,
+            0b01 => AddressBytes::ThreeOrFour,
+            0b10 => AddressBytes::Four,
+            _ => return None,
+        };
+
+        let supported = |bit: u32| dwords[0] & (1 << bit) != 0;
+
+        let quad_enable = match dwords.get(14).map(|dw| (dw >> 20) & 0x7) {
+            Some(0b000) => QuadEnable::NotRequired,
+            Some(0b010) => QuadEnable::Status6,
+            _ => QuadEnable::Unsupported,
+        };
+
+        // Tables predating the entry methods of DWORD16 are assumed to take
+        // the plain EN4B instruction
+        let four_byte_entry = match dwords.get(15).map(|dw| dw >> 24) {
+            _ if address_bytes == AddressBytes::Four => Some(FourByteEntry::Always),
+            None => Some(FourByteEntry::Instruction),
+            Some(methods) if methods & 0x01 != 0 => Some(FourByteEntry::Instruction),
+            Some(methods) if methods & 0x02 != 0 => Some(FourByteEntry::WriteEnableInstruction),
+            Some(methods) if methods & 0x40 != 0 => Some(FourByteEntry::Always),
+            Some(_) => None,
+        };
+
+        Some(Self {
+            // A density of exactly 4 GiB does not fit the 32 bit address space
+            capacity: capacity.min(u32::MAX as u64) as u32,
+            page_size,
+            erase_types: [
+                EraseType::parse(dwords[7]),
+                EraseType::parse(dwords[7] >> 16),
+                EraseType::parse(dwords[8]),
+                EraseType::parse(dwords[8] >> 16),
+            ],
+            address_bytes,
+            read_1_1_2: supported(16).then(|| FastRead::parse(dwords[3])),
+            read_1_2_2: supported(20).then(|| FastRead::parse(dwords[3] >> 16)),
+            read_1_1_4: supported(22).then(|| FastRead::parse(dwords[2] >> 16)),
+            read_1_4_4: supported(21).then(|| FastRead::parse(dwords[2])),
+            quad_enable,
+            four_byte_entry,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Basic flash parameter table of an IS25LP128
+    pub const IS25LP128: [u32; 16] = [
+        0xFFF9_20E5,
+        0x07FF_FFFF,
+        0x6B08_EB44,
+        0xBB42_3B08,
+        0xFFFF_FFEE,
+        0x00FF_FFFF,
+        0xEB44_FFFF,
+        0x520F_200C,
+        0x0000_D810,
+        0x0000_0000,
+        0x0000_0081,
+        0x0000_0000,
+        0x0000_0000,
+        0x0000_0000,
+        0x0020_0000,
+        0x0000_0000,
+    ];
+
+    #[test]
+    fn header() {
+        assert_eq!(
+            parse_header(&[0x53, 0x46, 0x44, 0x50, 0x06, 0x01, 0x01, 0xFF]),
+            Some(2)
+        );
+        assert_eq!(parse_header(&[0xFF; 8]), None);
+
+        let header = ParameterHeader::from([0x00, 0x06, 0x01, 0x10, 0x30, 0x00, 0x00, 0xFF]);
+        assert_eq!(header.id, BASIC_FLASH_PARAMETERS);
+        assert_eq!(header.length, 16);
+        assert_eq!(header.pointer, 0x30);
+    }
+
+    #[test]
+    fn basic_flash_parameters() {
+        let bfpt = BasicFlashParameters::parse(&IS25LP128).unwrap();
+
+        assert_eq!(bfpt.capacity, 16 * 1024 * 1024);
+        assert_eq!(bfpt.page_size, 256);
+        assert_eq!(
+            bfpt.erase_types,
+            [
+                Some(EraseType {
+                    size: 4096,
+                    opcode: 0x20
+                }),
+                Some(EraseType {
+                    size: 32 * 1024,
+                    opcode: 0x52
+                }),
+                Some(EraseType {
+                    size: 64 * 1024,
+                    opcode: 0xD8
+                }),
+                None,
+            ]
+        );
+        assert_eq!(bfpt.address_bytes, AddressBytes::Three);
+        assert_eq!(
+            bfpt.read_1_4_4,
+            Some(FastRead {
+                opcode: 0xEB,
+                dummy_cycles: 6
+            })
+        );
+        assert_eq!(
+            bfpt.read_1_1_4,
+            Some(FastRead {
+                opcode: 0x6B,
+                dummy_cycles: 8
+            })
+        );
+        assert_eq!(
+            bfpt.read_1_1_2,
+            Some(FastRead {
+                opcode: 0x3B,
+                dummy_cycles: 8
+            })
+        );
+        assert_eq!(
+            bfpt.read_1_2_2,
+            Some(FastRead {
+                opcode: 0xBB,
+                dummy_cycles: 4
+            })
+        );
+        assert_eq!(bfpt.quad_enable, QuadEnable::Status6);
+        assert_eq!(bfpt.four_byte_entry, None);
+    }
+
+    #[test]
+    fn legacy_table() {
+        let mut dwords = IS25LP128;
+        // 256 Mbit, 3 or 4 byte addressing, no quad reads
+        dwords[0] = 0xFF9B_20E5;
+        dwords[1] = 0x8000_001C;
+
+        let bfpt = BasicFlashParameters::parse(&dwords[..9]).unwrap();
+        assert_eq!(bfpt.capacity, 32 * 1024 * 1024);
+        assert_eq!(bfpt.page_size, 256);
+        assert_eq!(bfpt.address_bytes, AddressBytes::ThreeOrFour);
+        assert_eq!(bfpt.read_1_1_4, None);
+        assert_eq!(bfpt.read_1_4_4, None);
+        assert_eq!(bfpt.quad_enable, QuadEnable::Unsupported);
+        assert_eq!(bfpt.four_byte_entry, Some(FourByteEntry::Instruction));
+
+        assert_eq!(BasicFlashParameters::parse(&dwords[..8]), None);
+    }
+
+    #[test]
+    fn four_byte_entry() {
+        let mut dwords = IS25LP128;
+        dwords[0] = 0xFFFB_20E5;
+
+        dwords[15] = 0x0100_0000;
+        let bfpt = BasicFlashParameters::parse(&dwords).unwrap();
+        assert_eq!(bfpt.four_byte_entry, Some(FourByteEntry::Instruction));
+
+        dwords[15] = 0x0200_0000;
+        let bfpt = BasicFlashParameters::parse(&dwords).unwrap();
+        assert_eq!(
+            bfpt.four_byte_entry,
+            Some(FourByteEntry::WriteEnableInstruction)
+        );
+
+        // 4 byte addresses only
+        dwords[0] = 0xFFFD_20E5;
+        dwords[15] = 0x0000_0000;
+        let bfpt = BasicFlashParameters::parse(&dwords).unwrap();
+        assert_eq!(bfpt.address_bytes, AddressBytes::Four);
+        assert_eq!(bfpt.four_byte_entry, Some(FourByteEntry::Always));
+    }
+}
@@ -0,0 +1,184 @@
+//! [`Qspi`] implementation for flashes wired to a plain SPI peripheral, along
+//! with an `AsyncQspi` implementation for async SPI peripherals when the
+//! `async` feature is enabled
+//!
+//! Only single lane, single data rate commands can be expressed on a regular
+//! SPI bus. [`IS25xP`](crate::IS25xP) picks the single lane command set when
+//! used with this transport.
+
+use embedded_hal::spi::{Operation, SpiDevice};
+
+use crate::qspi::{Qspi, QspiMode, QspiReadCommand, QspiWriteCommand};
+
+#[derive(Debug)]
+pub enum SpiError<E> {
+    Spi(E),
+    /// The command uses more than one lane, double data rate or a number of
+    /// dummy cycles that is not a whole number of bytes
+    Unsupported,
+}
+
+pub struct SpiTransport<SPI> {
+    spi: SPI,
+}
+
+impl<SPI> SpiTransport<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self { spi }
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+/// Serialize the instruction, address and dummy phases of a command into
+/// `buf`, returning the number of bytes used
+fn write_header<E>(
+    buf: &mut [u8; 8],
+    instruction: Option<(u8, QspiMode)>,
+    address: Option<(u32, QspiMode)>,
+    address_bytes: usize,
+    dummy_cycles: u8,
+) -> Result<usize, SpiError<E>> {
+    let mut len = 0;
+
+    if let Some((instruction, mode)) = instruction {
+        if mode != QspiMode::SingleChannel {
+            return Err(SpiError::Unsupported);
+        }
+        buf[len] = instruction;
+        len += 1;
+    }
+
+    if let Some((address, mode)) = address {
+        if mode != QspiMode::SingleChannel {
+            return Err(SpiError::Unsupported);
+        }
+        buf[len..len + address_bytes].copy_from_slice(&address.to_be_bytes()[4 - address_bytes..]);
+        len += address_bytes;
+    }
+
+    if !dummy_cycles.is_multiple_of(8) {
+        return Err(SpiError::Unsupported);
+    }
+    let dummy_bytes = dummy_cycles as usize / 8;
+    if len + dummy_bytes > buf.len() {
+        return Err(SpiError::Unsupported);
+    }
+    buf[len..len + dummy_bytes].fill(0);
+    len += dummy_bytes;
+
+    Ok(len)
+}
+
+impl<SPI> Qspi for SpiTransport<SPI>
+where
+    SPI: SpiDevice,
+{
+    type Error = SpiError<SPI::Error>;
+
+    fn write(&mut self, cmd: QspiWriteCommand) -> Result<(), Self::Error> {
+        if cmd.double_data_rate {
+            return Err(SpiError::Unsupported);
+        }
+
+        let mut header = [0u8; 8];
+        let len = write_header(
+            &mut header,
+            cmd.instruction,
+            cmd.address,
+            cmd.address_size.bytes(),
+            cmd.dummy_cycles,
+        )?;
+
+        match cmd.data {
+            Some((_, mode)) if mode != QspiMode::SingleChannel => Err(SpiError::Unsupported),
+            Some((data, _)) => self
+                .spi
+                .transaction(&mut [Operation::Write(&header[..len]), Operation::Write(data)])
+                .map_err(SpiError::Spi),
+            None => self.spi.write(&header[..len]).map_err(SpiError::Spi),
+        }
+    }
+
+    fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if cmd.double_data_rate || cmd.data_mode != QspiMode::SingleChannel {
+            return Err(SpiError::Unsupported);
+        }
+
+        let mut header = [0u8; 8];
+        let len = write_header(
+            &mut header,
+            cmd.instruction,
+            cmd.address,
+            cmd.address_size.bytes(),
+            cmd.dummy_cycles,
+        )?;
+
+        self.spi
+            .transaction(&mut [Operation::Write(&header[..len]), Operation::Read(buf)])
+            .map_err(SpiError::Spi)
+    }
+
+    fn lanes(&self) -> QspiMode {
+        QspiMode::SingleChannel
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI> crate::asynch::AsyncQspi for SpiTransport<SPI>
+where
+    SPI: embedded_hal_async::spi::SpiDevice,
+{
+    type Error = SpiError<SPI::Error>;
+
+    async fn write(&mut self, cmd: QspiWriteCommand<'_>) -> Result<(), Self::Error> {
+        if cmd.double_data_rate {
+            return Err(SpiError::Unsupported);
+        }
+
+        let mut header = [0u8; 8];
+        let len = write_header(
+            &mut header,
+            cmd.instruction,
+            cmd.address,
+            cmd.address_size.bytes(),
+            cmd.dummy_cycles,
+        )?;
+
+        match cmd.data {
+            Some((_, mode)) if mode != QspiMode::SingleChannel => Err(SpiError::Unsupported),
+            Some((data, _)) => self
+                .spi
+                .transaction(&mut [Operation::Write(&header[..len]), Operation::Write(data)])
+                .await
+                .map_err(SpiError::Spi),
+            None => self.spi.write(&header[..len]).await.map_err(SpiError::Spi),
+        }
+    }
+
+    async fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if cmd.double_data_rate || cmd.data_mode != QspiMode::SingleChannel {
+            return Err(SpiError::Unsupported);
+        }
+
+        let mut header = [0u8; 8];
+        let len = write_header(
+            &mut header,
+            cmd.instruction,
+            cmd.address,
+            cmd.address_size.bytes(),
+            cmd.dummy_cycles,
+        )?;
+
+        self.spi
+            .transaction(&mut [Operation::Write(&header[..len]), Operation::Read(buf)])
+            .await
+            .map_err(SpiError::Spi)
+    }
+
+    fn lanes(&self) -> QspiMode {
+        QspiMode::SingleChannel
+    }
+}
@@ -1,3 +1,5 @@
+use crate::protection::{BlockProtection, ProtectionArea};
+
 pub struct Status(u8);
 
 impl From<u8> for Status {
@@ -8,11 +10,18 @@ impl From<u8> for Status {
 
 pub const WIP: u8 = 0x01;
 pub const WEL: u8 = 0x02;
+pub const BP: u8 = 0x3C;
+pub const BP_SHIFT: u8 = 2;
 pub const QE: u8 = 0x40;
 pub const SRWD: u8 = 0x80;
 
 #[allow(dead_code)]
 impl Status {
+    /// Raw register value
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
     /// Write In Progress Bit:
     /// - "0" indicates the device is ready (default)
     /// - "1" indicates a write cycle is in progress and the device is busy
@@ -30,8 +39,13 @@ impl Status {
     /// Block Protection Bit: (See Tables 6.4 for details)
     /// - "0" indicates the specific blocks are not write-protected (default)
     /// - "1" indicates the specific blocks are write-protected
-    pub fn bp(&self) {
-        todo!()
+    pub fn bp(&self) -> BlockProtection {
+        BlockProtection::new((self.0 & BP) >> BP_SHIFT).unwrap_or(BlockProtection::ALL)
+    }
+
+    /// Register value with the block protection bits replaced by `bp`
+    pub fn with_bp(&self, bp: BlockProtection) -> u8 {
+        (self.0 & !BP) | (bp.bits() << BP_SHIFT)
     }
 
     /// Quad Enable bit:
@@ -49,25 +63,325 @@ impl Status {
     }
 }
 
+pub struct Function(u8);
+
+impl From<u8> for Function {
+    fn from(v: u8) -> Self {
+        Self(v)
+    }
+}
+
+pub const TBS: u8 = 0x02;
+pub const PSUS: u8 = 0x04;
+pub const ESUS: u8 = 0x08;
+pub const IRL0: u8 = 0x10;
+
+impl Function {
+    /// Top/Bottom Selection, one time programmable:
+    /// - "0" indicates the Top area is protected by the BP bits (default)
+    /// - "1" indicates the Bottom area is protected by the BP bits
+    pub fn tbs(&self) -> ProtectionArea {
+        if self.0 & TBS != 0 {
+            ProtectionArea::Bottom
+        } else {
+            ProtectionArea::Top
+        }
+    }
+
+    /// Program Suspend bit:
+    /// - "0" indicates program is not suspended (default)
+    /// - "1" indicates program is suspended
+    pub fn psus(&self) -> bool {
+        self.0 & PSUS != 0
+    }
+
+    /// Erase Suspend bit:
+    /// - "0" indicates erase is not suspended (default)
+    /// - "1" indicates erase is suspended
+    pub fn esus(&self) -> bool {
+        self.0 & ESUS != 0
+    }
+
+    /// Information Row Lock bits, one time programmable:
+    /// - "0" indicates the information row `row` can be programmed (default)
+    /// - "1" indicates the information row `row` cannot be programmed
+    pub fn irl(&self, row: u8) -> bool {
+        self.0 & (IRL0 << row) != 0
+    }
+}
+
+pub const WRAP_LENGTH: u8 = 0x03;
+pub const WRAP_ENABLE: u8 = 0x04;
+pub const DUMMY_CYCLES: u8 = 0x78;
+pub const DUMMY_CYCLES_SHIFT: u8 = 3;
+
+/// Read parameters, as the value of the volatile read register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadParameters(u8);
+
+impl From<u8> for ReadParameters {
+    fn from(v: u8) -> Self {
+        Self(v)
+    }
+}
+
+impl ReadParameters {
+    /// Default read parameters after power-up
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Raw register value
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Dummy Cycles bits P6-P3:
+    /// - "0" selects the default number of dummy cycles of each read command
+    /// - any other value selects that many dummy cycles for the fast reads
+    pub const fn dummy_cycles(&self) -> u8 {
+        (self.0 & DUMMY_CYCLES) >> DUMMY_CYCLES_SHIFT
+    }
+
+    /// Parameters with `cycles` dummy cycles, or `None` if `cycles` does not
+    /// fit in 4 bits
+    pub const fn with_dummy_cycles(self, cycles: u8) -> Option<Self> {
+        if cycles <= 0xF {
+            Some(Self(
+                (self.0 & !DUMMY_CYCLES) | (cycles << DUMMY_CYCLES_SHIFT),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Burst Length Enable bit P2 and Burst Length bits P1-P0:
+    /// - `None` if reads continue through the whole array (default)
+    /// - the length of the aligned window reads wrap around in otherwise
+    pub const fn wrap(&self) -> Option<WrapLength> {
+        if self.0 & WRAP_ENABLE == 0 {
+            return None;
+        }
+
+        Some(match self.0 & WRAP_LENGTH {
+            0 => WrapLength::Bytes8,
+            1 => WrapLength::Bytes16,
+            2 => WrapLength::Bytes32,
+            _ => WrapLength::Bytes64,
+        })
+    }
+
+    /// Parameters wrapping reads around in windows of `wrap` bytes, or
+    /// reading through the whole array if `None`
+    pub const fn with_wrap(self, wrap: Option<WrapLength>) -> Self {
+        let bits = match wrap {
+            None => 0,
+            Some(length) => WRAP_ENABLE | length as u8,
+        };
+        Self((self.0 & !(WRAP_ENABLE | WRAP_LENGTH)) | bits)
+    }
+}
+
+/// Length of the window burst reads wrap around in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapLength {
+    Bytes8 = 0,
+    Bytes16 = 1,
+    Bytes32 = 2,
+    Bytes64 = 3,
+}
+
+pub const ODS: u8 = 0xE0;
+pub const ODS_SHIFT: u8 = 5;
+
+/// Output driver strength, as selected by the ODS2-ODS0 bits of the extended
+/// read register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveStrength {
+    Percent12_5 = 1,
+    Percent25 = 2,
+    Percent37_5 = 3,
+    Percent75 = 5,
+    Percent100 = 6,
+    /// Default after power-up
+    Percent50 = 7,
+}
+
+impl DriveStrength {
+    /// Drive strength selected by the ODS bits of `bits`, or `None` for the
+    /// reserved values
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        match (bits & ODS) >> ODS_SHIFT {
+            1 => Some(DriveStrength::Percent12_5),
+            2 => Some(DriveStrength::Percent25),
+            3 => Some(DriveStrength::Percent37_5),
+            5 => Some(DriveStrength::Percent75),
+            6 => Some(DriveStrength::Percent100),
+            7 => Some(DriveStrength::Percent50),
+            _ => None,
+        }
+    }
+
+    /// Register value `bits` with the ODS bits replaced
+    pub const fn apply(self, bits: u8) -> u8 {
+        (bits & !ODS) | ((self as u8) << ODS_SHIFT)
+    }
+}
+
+pub const PROT_E: u8 = 0x02;
+pub const P_ERR: u8 = 0x04;
+pub const E_ERR: u8 = 0x08;
+
+/// Output driver strength and error flags, as the value of the volatile
+/// extended read register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedReadParameters(u8);
+
+impl From<u8> for ExtendedReadParameters {
+    fn from(v: u8) -> Self {
+        Self(v)
+    }
+}
+
+impl ExtendedReadParameters {
+    /// Raw register value
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Output Driver Strength bits EB7-EB5, or `None` for the reserved values
+    pub const fn drive_strength(&self) -> Option<DriveStrength> {
+        DriveStrength::from_bits(self.0)
+    }
+
+    /// Register value with the ODS bits selecting `strength`
+    pub const fn with_drive_strength(self, strength: DriveStrength) -> Self {
+        Self(strength.apply(self.0))
+    }
+
+    /// Erase Error bit EB3:
+    /// - "0" indicates the last erase succeeded (default)
+    /// - "1" indicates an erase failed
+    pub const fn e_err(&self) -> bool {
+        self.0 & E_ERR != 0
+    }
+
+    /// Program Error bit EB2:
+    /// - "0" indicates the last program succeeded (default)
+    /// - "1" indicates a program failed
+    pub const fn p_err(&self) -> bool {
+        self.0 & P_ERR != 0
+    }
+
+    /// Protection Error bit EB1:
+    /// - "0" indicates no protection error (default)
+    /// - "1" indicates a program or erase targeted a protected region
+    pub const fn prot_e(&self) -> bool {
+        self.0 & PROT_E != 0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn bits() {
-        assert_eq!(Status(0b00000001).wip(), true);
-        assert_eq!(Status(0b00000010).wel(), true);
-        assert_eq!(Status(0b01000000).qe(), true);
-        assert_eq!(Status(0b10000000).srwd(), true);
-
-        assert_eq!(Status(0b11111111).wip(), true);
-        assert_eq!(Status(0b11111111).wel(), true);
-        assert_eq!(Status(0b11111111).qe(), true);
-        assert_eq!(Status(0b11111111).srwd(), true);
-
-        assert_eq!(Status(0b00000000).wip(), false);
-        assert_eq!(Status(0b00000000).wel(), false);
-        assert_eq!(Status(0b00000000).qe(), false);
-        assert_eq!(Status(0b00000000).srwd(), false);
+        assert!(Status(0b00000001).wip());
+        assert!(Status(0b00000010).wel());
+        assert!(Status(0b01000000).qe());
+        assert!(Status(0b10000000).srwd());
+
+        assert!(Status(0b11111111).wip());
+        assert!(Status(0b11111111).wel());
+        assert!(Status(0b11111111).qe());
+        assert!(Status(0b11111111).srwd());
+
+        assert!(!Status(0b00000000).wip());
+        assert!(!Status(0b00000000).wel());
+        assert!(!Status(0b00000000).qe());
+        assert!(!Status(0b00000000).srwd());
+    }
+
+    #[test]
+    fn block_protection() {
+        assert_eq!(Status(0b00000000).bp(), BlockProtection::NONE);
+        assert_eq!(Status(0b01000100).bp().bits(), 1);
+        assert_eq!(Status(0b00100000).bp().bits(), 8);
+        assert_eq!(Status(0b11111111).bp(), BlockProtection::ALL);
+
+        let bp = BlockProtection::new(0b0101).unwrap();
+        assert_eq!(Status(0b11000011).with_bp(bp), 0b11010111);
+        assert_eq!(
+            Status(0b11111111).with_bp(BlockProtection::NONE),
+            0b11000011
+        );
+    }
+
+    #[test]
+    fn function_bits() {
+        assert_eq!(Function(0b00000000).tbs(), ProtectionArea::Top);
+        assert_eq!(Function(0b00000010).tbs(), ProtectionArea::Bottom);
+
+        assert!(Function(0b00000100).psus());
+        assert!(Function(0b00001000).esus());
+        assert!(!Function(0b11110011).psus());
+        assert!(!Function(0b11110011).esus());
+
+        assert!(Function(0b00010000).irl(0));
+        assert!(Function(0b10000000).irl(3));
+        assert!(!Function(0b01100010).irl(0));
+        assert!(!Function(0b01100010).irl(3));
+    }
+
+    #[test]
+    fn read_parameters() {
+        let params = ReadParameters::new();
+        assert_eq!(params.dummy_cycles(), 0);
+        assert_eq!(params.wrap(), None);
+
+        let params = params.with_dummy_cycles(10).unwrap();
+        assert_eq!(params.bits(), 0b01010000);
+        assert_eq!(params.dummy_cycles(), 10);
+        assert_eq!(params.with_dummy_cycles(0x10), None);
+
+        let params = params.with_wrap(Some(WrapLength::Bytes32));
+        assert_eq!(params.bits(), 0b01010110);
+        assert_eq!(params.wrap(), Some(WrapLength::Bytes32));
+        assert_eq!(params.with_wrap(None).bits(), 0b01010000);
+        assert_eq!(ReadParameters(0b00000011).wrap(), None);
+    }
+
+    #[test]
+    fn drive_strength() {
+        assert_eq!(
+            DriveStrength::from_bits(0b11100000),
+            Some(DriveStrength::Percent50)
+        );
+        assert_eq!(
+            DriveStrength::from_bits(0b00111111),
+            Some(DriveStrength::Percent12_5)
+        );
+        assert_eq!(DriveStrength::from_bits(0b10000000), None);
+        assert_eq!(DriveStrength::Percent100.apply(0b11111111), 0b11011111);
+        assert_eq!(DriveStrength::Percent25.apply(0b00000001), 0b01000001);
+    }
+
+    #[test]
+    fn extended_read() {
+        let erp = ExtendedReadParameters::from(0b11101010);
+        assert_eq!(erp.drive_strength(), Some(DriveStrength::Percent50));
+        assert!(erp.e_err());
+        assert!(!erp.p_err());
+        assert!(erp.prot_e());
+
+        let erp = ExtendedReadParameters::from(0b00000100);
+        assert_eq!(erp.drive_strength(), None);
+        assert!(!erp.e_err());
+        assert!(erp.p_err());
+        assert!(!erp.prot_e());
+
+        let erp = erp.with_drive_strength(DriveStrength::Percent75);
+        assert_eq!(erp.bits(), 0b10100100);
     }
 }
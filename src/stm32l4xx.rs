@@ -0,0 +1,85 @@
+//! [`Qspi`] implementation for the QUADSPI peripheral of the STM32L4 family
+
+use stm32l4xx_hal::{
+    pac::QUADSPI,
+    qspi::{self as hal, ClkPin, IO0Pin, IO1Pin, IO2Pin, IO3Pin, NCSPin, QspiError},
+};
+
+use crate::qspi::{AddressSize, Qspi, QspiMode, QspiReadCommand, QspiWriteCommand};
+
+fn mode(mode: QspiMode) -> hal::QspiMode {
+    match mode {
+        QspiMode::SingleChannel => hal::QspiMode::SingleChannel,
+        QspiMode::DualChannel => hal::QspiMode::DualChannel,
+        QspiMode::QuadChannel => hal::QspiMode::QuadChannel,
+    }
+}
+
+fn address_size(size: AddressSize) -> hal::AddressSize {
+    match size {
+        AddressSize::Addr24Bit => hal::AddressSize::Addr24Bit,
+        AddressSize::Addr32Bit => hal::AddressSize::Addr32Bit,
+    }
+}
+
+impl<CLK, NCS, IO0, IO1, IO2, IO3> Qspi for hal::Qspi<(CLK, NCS, IO0, IO1, IO2, IO3)>
+where
+    CLK: ClkPin<QUADSPI>,
+    NCS: NCSPin<QUADSPI>,
+    IO0: IO0Pin<QUADSPI>,
+    IO1: IO1Pin<QUADSPI>,
+    IO2: IO2Pin<QUADSPI>,
+    IO3: IO3Pin<QUADSPI>,
+{
+    type Error = QspiError;
+
+    fn write(&mut self, cmd: QspiWriteCommand) -> Result<(), Self::Error> {
+        // The address width is part of the peripheral configuration rather
+        // than of the individual command
+        if cmd.address.is_some() {
+            let config = self
+                .get_config()
+                .address_size(address_size(cmd.address_size));
+            self.apply_config(config);
+        }
+
+        hal::Qspi::write(
+            self,
+            hal::QspiWriteCommand {
+                instruction: cmd.instruction.map(|(i, m)| (i, mode(m))),
+                address: cmd.address.map(|(a, m)| (a, mode(m))),
+                alternative_bytes: None,
+                dummy_cycles: cmd.dummy_cycles,
+                data: cmd.data.map(|(d, m)| (d, mode(m))),
+                double_data_rate: cmd.double_data_rate,
+            },
+        )
+    }
+
+    fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if cmd.address.is_some() {
+            let config = self
+                .get_config()
+                .address_size(address_size(cmd.address_size));
+            self.apply_config(config);
+        }
+
+        hal::Qspi::transfer(
+            self,
+            hal::QspiReadCommand {
+                instruction: cmd.instruction.map(|(i, m)| (i, mode(m))),
+                address: cmd.address.map(|(a, m)| (a, mode(m))),
+                alternative_bytes: None,
+                dummy_cycles: cmd.dummy_cycles,
+                data_mode: mode(cmd.data_mode),
+                receive_length: cmd.receive_length,
+                double_data_rate: cmd.double_data_rate,
+            },
+            buf,
+        )
+    }
+
+    fn double_data_rate(&self) -> bool {
+        true
+    }
+}
@@ -3,20 +3,115 @@ mod it_should {
     use std::{cell::RefCell, collections::VecDeque};
 
     use crate::commands::{
-        ERASE_BLOCK, ERASE_CHIP, ERASE_HALF_BLOCK, ERASE_SECTOR, QUAD_WRITE, WRITE_ENABLE,
-        WRITE_STATUS,
+        ERASE_BLOCK, ERASE_CHIP, ERASE_HALF_BLOCK, ERASE_SECTOR, QUAD_WRITE, WRITE_DISABLE,
+        WRITE_ENABLE, WRITE_STATUS,
     };
 
+    use crate::flash_params::{BLOCK_SIZE, HALFBLOCK_SIZE, SECTOR_SIZE};
     use crate::*;
 
+    /// JEDEC ID of an IS25LP128
+    const IS25LP128: [u8; 3] = [0x9D, 0x60, 0x18];
+
+    const UNIQUE_ID: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+        0xFF,
+    ];
+
     struct MockQspi {
         write_operations: RefCell<VecDeque<(Option<(u8, QspiMode)>, Option<u32>, Option<usize>)>>,
+        jedec_id: [u8; 3],
+        sfdp: Vec<u8>,
+        status: u8,
+        function: u8,
+        /// Drop program and erase commands, as a protected device would
+        locked: bool,
+        /// Number of status reads reporting an erase as in progress
+        erase_polls: u32,
+        busy: u32,
+        suspended: u32,
+        /// Contents of the memory array, when emulated
+        memory: Option<Vec<u8>>,
+        /// Address of a worn out cell, which stays programmed when erased
+        worn: Option<u32>,
+        /// Opcode and dummy cycles of the reads of the memory array
+        reads: Vec<(u8, u8)>,
+        /// Whether the device is in QPI mode, ignoring commands whose
+        /// instruction is not sent on four lanes, and the other way around
+        qpi: bool,
+        read_parameters: u8,
+        extended_read: u8,
+        /// Error flags of the extended read register set by each program and
+        /// erase
+        fault: u8,
     }
 
     impl MockQspi {
         pub fn new() -> Self {
+            Self::with_id(IS25LP128)
+        }
+
+        pub fn with_id(jedec_id: [u8; 3]) -> Self {
             Self {
                 write_operations: RefCell::new(VecDeque::new()),
+                jedec_id,
+                sfdp: Vec::new(),
+                status: 0,
+                function: 0,
+                locked: false,
+                erase_polls: 0,
+                busy: 0,
+                suspended: 0,
+                memory: None,
+                worn: None,
+                reads: Vec::new(),
+                qpi: false,
+                read_parameters: 0,
+                extended_read: 0xE0,
+                fault: 0,
+            }
+        }
+
+        /// Emulate the contents of a memory array of `capacity` bytes
+        pub fn with_memory(jedec_id: [u8; 3], capacity: usize) -> Self {
+            Self {
+                memory: Some(vec![0xFF; capacity]),
+                ..Self::with_id(jedec_id)
+            }
+        }
+
+        /// Expose `bfpt` as the only parameter table in the SFDP space
+        pub fn with_sfdp(jedec_id: [u8; 3], bfpt: &[u32]) -> Self {
+            let mut sfdp = vec![0x53, 0x46, 0x44, 0x50, 0x06, 0x01, 0x00, 0xFF];
+            sfdp.extend_from_slice(&[0x00, 0x06, 0x01, bfpt.len() as u8, 0x10, 0x00, 0x00, 0xFF]);
+            for dw in bfpt {
+                sfdp.extend_from_slice(&dw.to_le_bytes());
+            }
+
+            Self {
+                sfdp,
+                ..Self::with_id(jedec_id)
+            }
+        }
+
+        fn program(&mut self, address: u32, data: &[u8]) {
+            if let Some(memory) = &mut self.memory {
+                let address = address as usize;
+                for (cell, byte) in memory[address..].iter_mut().zip(data) {
+                    *cell &= byte;
+                }
+            }
+        }
+
+        fn erase(&mut self, address: u32, size: usize) {
+            if let Some(memory) = &mut self.memory {
+                let start = address as usize / size * size;
+                memory[start..start + size].fill(0xFF);
+                if let Some(worn) = self.worn {
+                    if (start..start + size).contains(&(worn as usize)) {
+                        memory[worn as usize] = 0x00;
+                    }
+                }
             }
         }
     }
@@ -24,20 +119,165 @@ mod it_should {
     impl Qspi for MockQspi {
         type Error = ();
 
-        fn write(&self, cmd: QspiWriteCommand) -> Result<(), Self::Error> {
+        fn write(&mut self, cmd: QspiWriteCommand) -> Result<(), Self::Error> {
             self.write_operations.borrow_mut().push_front((
                 cmd.instruction,
                 cmd.address.map(|a| a.0),
                 cmd.data.map(|d| d.0.len()),
             ));
+            if self.qpi != matches!(cmd.instruction, Some((_, QspiMode::QuadChannel))) {
+                return Ok(());
+            }
+            match (cmd.instruction, cmd.data) {
+                (Some((0x35, _)), _) => self.qpi = true,
+                (Some((0xF5, _)), _) => self.qpi = false,
+                (Some((0x06, _)), _) => self.status |= 0x02,
+                (Some((0x04, _)), _) => self.status &= !0x02,
+                (Some((0x01, _)), Some((data, _))) => self.status = data[0],
+                (Some((0xC0, _)), Some((data, _))) => self.read_parameters = data[0],
+                (Some((0x83, _)), Some((data, _))) => self.extended_read = data[0],
+                (Some((0x82, _)), _) => self.extended_read &= !0x0E,
+                (Some((0x42, _)), Some((data, _))) => {
+                    self.function |= data[0];
+                    self.status &= !0x02;
+                }
+                (Some((0x75, _)), _) => {
+                    self.suspended = core::mem::take(&mut self.busy);
+                    self.function |= 0x08;
+                }
+                (Some((0x7A, _)), _) => {
+                    self.busy = core::mem::take(&mut self.suspended);
+                    self.function &= !0x08;
+                }
+                _ if self.locked => {}
+                (Some((opcode @ (0x20 | 0x21 | 0x52 | 0x5C | 0xD7 | 0xD8 | 0xDC), _)), _) => {
+                    let address = cmd.address.map_or(0, |a| a.0);
+                    let size = match opcode {
+                        0x52 | 0x5C => HALFBLOCK_SIZE,
+                        0xD8 | 0xDC => BLOCK_SIZE,
+                        _ => SECTOR_SIZE,
+                    };
+                    self.erase(address, size as usize);
+                    self.busy = self.erase_polls;
+                    self.status &= !0x02;
+                    self.extended_read |= self.fault;
+                }
+                (Some((0x60 | 0xC7, _)), _) => {
+                    let size = self.memory.as_ref().map_or(0, Vec::len);
+                    self.erase(0, size);
+                    self.busy = self.erase_polls;
+                    self.status &= !0x02;
+                    self.extended_read |= self.fault;
+                }
+                (Some((0x62 | 0x64, _)), _) => {
+                    self.status &= !0x02;
+                    self.extended_read |= self.fault;
+                }
+                (Some((0x02 | 0x12 | 0x32 | 0x34 | 0x38, _)), Some((data, _))) => {
+                    self.program(cmd.address.map_or(0, |a| a.0), data);
+                    self.status &= !0x02;
+                    self.extended_read |= self.fault;
+                }
+                _ => self.status &= !0x02,
+            }
             Ok(())
         }
 
-        fn transfer(&self, _cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
-            // Make sure we do not get stuck in `wait_busy` state
-            buf[0] = 0;
+        fn transfer(&mut self, cmd: QspiReadCommand, buf: &mut [u8]) -> Result<(), Self::Error> {
+            if self.qpi != matches!(cmd.instruction, Some((_, QspiMode::QuadChannel))) {
+                buf.fill(0xFF);
+                return Ok(());
+            }
+            match cmd.instruction {
+                Some((0x9F | 0xAF, _)) => buf.copy_from_slice(&self.jedec_id),
+                Some((0x4B, _)) => buf.copy_from_slice(&UNIQUE_ID),
+                Some((0x05, _)) if self.busy > 0 => {
+                    self.busy -= 1;
+                    buf[0] = self.status | 0x01;
+                }
+                Some((0x05, _)) => buf[0] = self.status,
+                Some((0x48, _)) => buf[0] = self.function,
+                Some((0x61, _)) => buf[0] = self.read_parameters,
+                Some((0x81, _)) => buf[0] = self.extended_read,
+                Some((0x5A, _)) => {
+                    let address = cmd.address.unwrap().0 as usize;
+                    for (i, b) in buf.iter_mut().enumerate() {
+                        *b = *self.sfdp.get(address + i).unwrap_or(&0xFF);
+                    }
+                }
+                Some((
+                    opcode @ (0x03 | 0x0B | 0x3B | 0xBB | 0x6B | 0xEB | 0x0D | 0xBD | 0xED | 0x13
+                    | 0x0C | 0x3C | 0xBC | 0x6C | 0xEC | 0x0E | 0xBE | 0xEE),
+                    _,
+                )) => {
+                    let dtr = matches!(opcode, 0x0D | 0xBD | 0xED | 0x0E | 0xBE | 0xEE);
+                    assert_eq!(cmd.double_data_rate, dtr);
+                    self.reads.push((opcode, cmd.dummy_cycles));
+                    if let Some(memory) = &self.memory {
+                        let address = cmd.address.unwrap().0 as usize;
+                        buf.copy_from_slice(&memory[address..address + buf.len()]);
+                    }
+                }
+                // Make sure we do not get stuck in `wait_busy` state
+                _ => buf[0] = 0,
+            }
             Ok(())
         }
+
+        fn double_data_rate(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn detect_device() {
+        let dev = IS25xP::try_new(MockQspi::with_id([0x9D, 0x70, 0x17])).unwrap();
+
+        assert_eq!(dev.jedec_id().manufacturer(), 0x9D);
+        assert_eq!(dev.jedec_id().memory_type(), 0x70);
+        assert_eq!(dev.jedec_id().capacity(), 0x17);
+    }
+
+    #[test]
+    fn reject_unknown_device() {
+        let id = [0xEF, 0x40, 0x18];
+        match IS25xP::try_new(MockQspi::with_id(id)) {
+            Err(Error::UnknownDevice(found)) => assert_eq!(found, JedecId::from(id)),
+            _ => panic!("expected `Error::UnknownDevice`"),
+        }
+    }
+
+    /// Transport failing every command
+    struct FailingQspi;
+
+    impl Qspi for FailingQspi {
+        type Error = &'static str;
+
+        fn write(&mut self, _cmd: QspiWriteCommand) -> Result<(), Self::Error> {
+            Err("FIFO overrun")
+        }
+
+        fn transfer(&mut self, _cmd: QspiReadCommand, _buf: &mut [u8]) -> Result<(), Self::Error> {
+            Err("FIFO overrun")
+        }
+    }
+
+    #[test]
+    fn preserve_transport_errors() {
+        use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
+
+        let err = IS25xP::try_new(FailingQspi).err().unwrap();
+        assert!(matches!(err, Error::Qspi("FIFO overrun")));
+        assert_eq!(err.kind(), NorFlashErrorKind::Other);
+        assert_eq!(err.to_string(), "transport error: \"FIFO overrun\"");
+
+        let err: Error<()> = Error::OutOfBounds;
+        assert_eq!(err.kind(), NorFlashErrorKind::OutOfBounds);
+        assert_eq!(Error::<()>::Alignment.kind(), NorFlashErrorKind::NotAligned);
+        assert_eq!(
+            Error::<()>::UnknownDevice(JedecId::from([0xEF, 0x40, 0x18])).to_string(),
+            "unknown device EF 40 18"
+        );
     }
 
     #[test]
@@ -50,6 +290,985 @@ mod it_should {
         assert_eq!(dev.capacity(), expected);
     }
 
+    #[test]
+    fn have_detected_capacity() {
+        let dev = IS25xP::try_new(MockQspi::with_id([0x9D, 0x60, 0x15])).unwrap();
+        assert_eq!(dev.capacity(), 2 * 1024 * 1024);
+        assert_eq!(dev.memory_map().blocks().count(), 32);
+        assert_eq!(dev.memory_map().sectors().count(), 512);
+
+        let dev = IS25xP::try_new(MockQspi::with_id([0x9D, 0x70, 0x1A])).unwrap();
+        assert_eq!(dev.capacity(), 64 * 1024 * 1024);
+        assert_eq!(dev.memory_map().blocks().count(), 1024);
+    }
+
+    #[test]
+    fn have_configured_capacity() {
+        let dev = IS25xP::try_new_with_density(MockQspi::new(), Density::Mbit32).unwrap();
+        assert_eq!(dev.capacity(), 4 * 1024 * 1024);
+    }
+
+    #[test]
+    fn reject_out_of_bounds() {
+        let mut dev = IS25xP::try_new(MockQspi::with_id([0x9D, 0x60, 0x15])).unwrap();
+        let end = dev.memory_map().end();
+
+        assert!(dev.memory_map().sector_at(end - 1).is_some());
+        assert!(dev.memory_map().sector_at(end).is_none());
+
+        let mut buf = [0u8; 2];
+        assert!(matches!(
+            dev.read(end - 1, &mut buf),
+            Err(Error::OutOfBounds)
+        ));
+        assert!(matches!(dev.write(end - 1, &buf), Err(Error::OutOfBounds)));
+        assert!(matches!(
+            dev.erase(end, end + SECTOR_SIZE),
+            Err(Error::OutOfBounds)
+        ));
+    }
+
+    /// 8 MiB device with 64 byte pages and without a half block erase
+    struct SmallPageParams;
+
+    impl FlashParams for SmallPageParams {
+        fn page_size(&self) -> u32 {
+            64
+        }
+        fn sector_size(&self) -> u32 {
+            SECTOR_SIZE
+        }
+        fn halfblock_size(&self) -> u32 {
+            SECTOR_SIZE
+        }
+        fn block_size(&self) -> u32 {
+            BLOCK_SIZE
+        }
+        fn capacity(&self) -> u32 {
+            8 * 1024 * 1024
+        }
+        fn sector_erase_command(&self) -> QspiWriteCommand<'static> {
+            ERASE_SECTOR.instruction(0x20, QspiMode::SingleChannel)
+        }
+        fn halfblock_erase_command(&self) -> QspiWriteCommand<'static> {
+            self.sector_erase_command()
+        }
+        fn block_erase_command(&self) -> QspiWriteCommand<'static> {
+            ERASE_BLOCK
+        }
+        fn read_command(&self, _lanes: QspiMode) -> QspiReadCommand {
+            commands::READ
+        }
+        fn program_command(&self, _lanes: QspiMode) -> QspiWriteCommand<'static> {
+            commands::WRITE
+        }
+    }
+
+    #[test]
+    fn use_custom_params() {
+        let mut dev = IS25xP::try_new_with_params(MockQspi::new(), SmallPageParams).unwrap();
+
+        assert_eq!(dev.capacity(), 8 * 1024 * 1024);
+        let block = dev.memory_map().block_at(BLOCK_SIZE).unwrap();
+        assert_eq!(block.start(), BLOCK_SIZE);
+        assert_eq!(block.sectors().count(), 16);
+        assert_eq!(block.pages().count(), 1024);
+
+        dev.write(0x20, &[0u8; 64]).unwrap();
+        dev.erase(BLOCK_SIZE - SECTOR_SIZE * 2, BLOCK_SIZE * 2)
+            .unwrap();
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+            (WRITE_ENABLE.instruction, None, None),
+            (commands::WRITE.instruction, Some(0x20), Some(32)),
+            (WRITE_ENABLE.instruction, None, None),
+            (commands::WRITE.instruction, Some(0x40), Some(32)),
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                Some((0x20, QspiMode::SingleChannel)),
+                Some(BLOCK_SIZE - SECTOR_SIZE * 2),
+                None,
+            ),
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                Some((0x20, QspiMode::SingleChannel)),
+                Some(BLOCK_SIZE - SECTOR_SIZE),
+                None,
+            ),
+            (WRITE_ENABLE.instruction, None, None),
+            (ERASE_BLOCK.instruction, Some(BLOCK_SIZE), None),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn discover_params_from_sfdp() {
+        // Second source part with 4 KiB and 64 KiB erase types only
+        let mut bfpt = crate::sfdp::tests::IS25LP128;
+        bfpt[7] = 0xD810_200C;
+        bfpt[8] = 0x0000_0000;
+
+        let mut dev = IS25xP::try_new_auto(MockQspi::with_sfdp([0xEF, 0x40, 0x18], &bfpt)).unwrap();
+
+        assert_eq!(dev.capacity(), 16 * 1024 * 1024);
+        assert_eq!(dev.params().halfblock_size(), SECTOR_SIZE);
+        assert_eq!(
+            dev.params().read_command(QspiMode::QuadChannel).instruction,
+            Some((0xEB, QspiMode::SingleChannel))
+        );
+        assert_eq!(
+            dev.params()
+                .read_command(QspiMode::SingleChannel)
+                .instruction,
+            Some((0x0B, QspiMode::SingleChannel))
+        );
+
+        dev.erase(BLOCK_SIZE - SECTOR_SIZE, BLOCK_SIZE * 2).unwrap();
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                Some((0x20, QspiMode::SingleChannel)),
+                Some(BLOCK_SIZE - SECTOR_SIZE),
+                None,
+            ),
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                Some((0xD8, QspiMode::SingleChannel)),
+                Some(BLOCK_SIZE),
+                None,
+            ),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn enter_four_byte_mode() {
+        // 256 Mbit part supporting 3 and 4 byte addresses, entering 4 byte
+        // address mode with EN4B
+        let mut bfpt = crate::sfdp::tests::IS25LP128;
+        bfpt[0] = 0xFFFB_20E5;
+        bfpt[1] = 0x0FFF_FFFF;
+        bfpt[15] = 0x0100_0000;
+
+        let dev = IS25xP::try_new_auto(MockQspi::with_sfdp([0xEF, 0x40, 0x19], &bfpt)).unwrap();
+        assert_eq!(dev.address_mode(), AddressMode::FourByteMode);
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (commands::ENTER_4BYTE_ADDRESS.instruction, None, None),
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+        drop(operations);
+
+        // EN4B preceded by a write enable
+        bfpt[15] = 0x0200_0000;
+        let dev = IS25xP::try_new_auto(MockQspi::with_sfdp([0xEF, 0x40, 0x19], &bfpt)).unwrap();
+        let operations = dev.qspi.write_operations.borrow();
+        assert_eq!(operations[operations.len() - 1].0, WRITE_ENABLE.instruction);
+        assert_eq!(
+            operations[operations.len() - 2].0,
+            commands::ENTER_4BYTE_ADDRESS.instruction
+        );
+        drop(operations);
+
+        // 4 byte addresses only, without any entry command
+        bfpt[0] = 0xFFFD_20E5;
+        bfpt[15] = 0x0000_0000;
+        let dev = IS25xP::try_new_auto(MockQspi::with_sfdp([0xEF, 0x40, 0x19], &bfpt)).unwrap();
+        assert_eq!(dev.address_mode(), AddressMode::FourByteMode);
+        assert!(!dev
+            .qspi
+            .write_operations
+            .borrow()
+            .iter()
+            .any(|op| op.0 == commands::ENTER_4BYTE_ADDRESS.instruction));
+
+        // No supported entry method
+        bfpt[0] = 0xFFFB_20E5;
+        assert!(matches!(
+            IS25xP::try_new_auto(MockQspi::with_sfdp([0xEF, 0x40, 0x19], &bfpt)),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn reject_missing_sfdp() {
+        assert!(matches!(
+            IS25xP::try_new_auto(MockQspi::new()),
+            Err(Error::Sfdp)
+        ));
+    }
+
+    #[test]
+    fn reject_issi_registers_on_other_devices() {
+        let mut dev = IS25xP::try_new_auto(MockQspi::with_sfdp(
+            [0xEF, 0x40, 0x18],
+            &crate::sfdp::tests::IS25LP128,
+        ))
+        .unwrap();
+        let configured = dev.qspi.write_operations.borrow().len();
+
+        assert!(matches!(dev.suspend(), Err(Error::Unsupported)));
+        assert!(matches!(
+            dev.set_protection_area(ProtectionArea::Bottom),
+            Err(Error::Unsupported)
+        ));
+        assert!(matches!(dev.lock_otp(0), Err(Error::Unsupported)));
+        assert!(matches!(
+            dev.set_drive_strength(DriveStrength::Percent100),
+            Err(Error::Unsupported)
+        ));
+        assert_eq!(dev.qspi.write_operations.borrow().len(), configured);
+    }
+
+    #[test]
+    fn set_block_protection() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        assert_eq!(dev.protected_range().unwrap(), None);
+
+        let bp = BlockProtection::covering(2);
+        dev.set_block_protection(bp).unwrap();
+        assert_eq!(dev.block_protection().unwrap(), bp);
+        assert_eq!(
+            dev.protected_range().unwrap(),
+            Some(dev.memory_map().end() - 2 * BLOCK_SIZE..dev.memory_map().end())
+        );
+
+        // The quad enable bit is preserved
+        assert_eq!(dev.qspi.status, 0x40 | (2 << 2));
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn protect_bottom_area() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+
+        dev.set_block_protection(BlockProtection::covering(16))
+            .unwrap();
+        dev.set_protection_area(ProtectionArea::Bottom).unwrap();
+        assert_eq!(dev.qspi.function, 0x02);
+        assert_eq!(dev.protected_range().unwrap(), Some(0..16 * BLOCK_SIZE));
+
+        assert!(dev.set_protection_area(ProtectionArea::Bottom).is_ok());
+        assert!(matches!(
+            dev.set_protection_area(ProtectionArea::Top),
+            Err(Error::Otp)
+        ));
+
+        dev.set_block_protection(BlockProtection::ALL).unwrap();
+        assert_eq!(
+            dev.protected_range().unwrap(),
+            Some(0..dev.memory_map().end())
+        );
+    }
+
+    #[test]
+    fn refuse_protected_writes() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        dev.set_block_protection(BlockProtection::covering(1))
+            .unwrap();
+        dev.qspi.write_operations.borrow_mut().clear();
+
+        let end = dev.memory_map().end();
+        assert!(matches!(
+            dev.write(end - 1, &[0x00]),
+            Err(Error::WriteProtected)
+        ));
+        assert!(matches!(
+            dev.erase(
+                end - BLOCK_SIZE - SECTOR_SIZE,
+                end - BLOCK_SIZE + SECTOR_SIZE
+            ),
+            Err(Error::WriteProtected)
+        ));
+        assert!(matches!(dev.erase(0, end), Err(Error::WriteProtected)));
+        let block = dev.memory_map().block_at(end - 1).unwrap();
+        assert!(matches!(
+            dev.erase_block(&block),
+            Err(Error::WriteProtected)
+        ));
+        assert!(dev.qspi.write_operations.borrow().is_empty());
+
+        dev.write(end - BLOCK_SIZE - 1, &[0x00]).unwrap();
+        dev.erase(0, end - BLOCK_SIZE).unwrap();
+    }
+
+    #[test]
+    fn detect_dropped_writes() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        dev.qspi.locked = true;
+
+        assert!(matches!(
+            dev.write(0x100, &[0x00]),
+            Err(Error::WriteProtected)
+        ));
+        assert!(matches!(
+            dev.erase(0, SECTOR_SIZE),
+            Err(Error::WriteProtected)
+        ));
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+            (WRITE_ENABLE.instruction, None, None),
+            (QUAD_WRITE.instruction, Some(0x100), Some(1)),
+            (WRITE_DISABLE.instruction, None, None),
+            (WRITE_ENABLE.instruction, None, None),
+            (ERASE_SECTOR.instruction, Some(0), None),
+            (WRITE_DISABLE.instruction, None, None),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn preserve_status_register() {
+        let dev = IS25xP::try_new(MockQspi {
+            status: 0x0C,
+            ..MockQspi::new()
+        })
+        .unwrap();
+        assert_eq!(dev.qspi.status, 0x4C);
+
+        // Quad enable already set
+        let dev = IS25xP::try_new(MockQspi {
+            status: 0x4C,
+            ..MockQspi::new()
+        })
+        .unwrap();
+        assert_eq!(dev.qspi.status, 0x4C);
+        assert!(dev.qspi.write_operations.borrow().is_empty());
+    }
+
+    /// Records the level of the WP# pin
+    struct MockPin(bool);
+
+    impl embedded_hal::digital::ErrorType for MockPin {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_hal::digital::OutputPin for MockPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.0 = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.0 = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lock_status_register() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        let mut wp = MockPin(true);
+
+        dev.set_block_protection(BlockProtection::ALL).unwrap();
+
+        // WP# is a data line while quad enable is set
+        assert!(matches!(
+            dev.lock_status_register(&mut wp),
+            Err(Error::Unsupported)
+        ));
+        assert!(!dev.status_register_locked().unwrap());
+        assert!(wp.0);
+
+        dev.qspi.status &= !0x40;
+        dev.lock_status_register(&mut wp).unwrap();
+        assert!(dev.status_register_locked().unwrap());
+        assert_eq!(dev.qspi.status, 0xBC);
+        assert!(!wp.0);
+
+        dev.unlock_status_register(&mut wp).unwrap();
+        assert!(!dev.status_register_locked().unwrap());
+        assert_eq!(dev.qspi.status, 0x3C);
+        assert!(wp.0);
+    }
+
+    #[test]
+    fn access_information_rows() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        dev.qspi.write_operations.borrow_mut().clear();
+
+        let mut buf = [0xFFu8; 16];
+        dev.read_otp(1, 0x10, &mut buf).unwrap();
+        dev.write_otp(2, 0x80, &buf).unwrap();
+        dev.erase_otp(3).unwrap();
+
+        assert!(matches!(
+            dev.read_otp(OTP_ROWS, 0, &mut buf),
+            Err(Error::OutOfBounds)
+        ));
+        assert!(matches!(
+            dev.write_otp(0, OTP_ROW_SIZE - 1, &buf),
+            Err(Error::OutOfBounds)
+        ));
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                commands::PROGRAM_INFORMATION_ROW.instruction,
+                Some(0x2080),
+                Some(buf.len()),
+            ),
+            (WRITE_ENABLE.instruction, None, None),
+            (
+                commands::ERASE_INFORMATION_ROW.instruction,
+                Some(0x3000),
+                None,
+            ),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn lock_information_rows() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+
+        dev.lock_otp(1).unwrap();
+        assert_eq!(dev.qspi.function, 0x20);
+        assert!(dev.otp_locked(1).unwrap());
+        assert!(!dev.otp_locked(0).unwrap());
+
+        assert!(matches!(
+            dev.write_otp(1, 0, &[0x00]),
+            Err(Error::WriteProtected)
+        ));
+        assert!(matches!(dev.erase_otp(1), Err(Error::WriteProtected)));
+        dev.write_otp(0, 0, &[0x00]).unwrap();
+    }
+
+    #[test]
+    fn read_unique_id() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        assert_eq!(dev.unique_id().unwrap(), UNIQUE_ID);
+    }
+
+    #[test]
+    fn suspend_erase() {
+        let mut dev = IS25xP::try_new(MockQspi {
+            erase_polls: 10,
+            ..MockQspi::new()
+        })
+        .unwrap();
+        dev.qspi.write_operations.borrow_mut().clear();
+
+        let block = dev.memory_map().block_at(0).unwrap();
+        dev.start_erase_block(&block).unwrap();
+
+        let suspended = dev.suspend().unwrap();
+        assert_eq!(
+            suspended,
+            Some(Operation::Erase {
+                start: 0,
+                end: BLOCK_SIZE
+            })
+        );
+        assert_eq!(dev.suspended(), suspended);
+
+        // Outside of the suspended erase
+        let mut buf = [0u8; 4];
+        dev.read(BLOCK_SIZE, &mut buf).unwrap();
+        dev.write(BLOCK_SIZE, &buf).unwrap();
+
+        // Forbidden while suspended
+        assert!(matches!(dev.read(0, &mut buf), Err(Error::Suspended)));
+        assert!(matches!(dev.write(0, &buf), Err(Error::Suspended)));
+        assert!(matches!(
+            dev.erase(BLOCK_SIZE, 2 * BLOCK_SIZE),
+            Err(Error::Suspended)
+        ));
+        assert!(matches!(
+            dev.set_block_protection(BlockProtection::ALL),
+            Err(Error::Suspended)
+        ));
+        assert!(matches!(dev.wait(), Err(Error::Suspended)));
+
+        dev.resume().unwrap();
+        assert_eq!(dev.suspended(), None);
+        dev.wait().unwrap();
+        assert_eq!(dev.qspi.busy, 0);
+        assert_eq!(dev.suspend().unwrap(), None);
+
+        let operations = dev.qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (ERASE_BLOCK.instruction, Some(0), None),
+            (commands::SUSPEND.instruction, None, None),
+            (WRITE_ENABLE.instruction, None, None),
+            (QUAD_WRITE.instruction, Some(BLOCK_SIZE), Some(buf.len())),
+            (commands::RESUME.instruction, None, None),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[test]
+    fn poll_started_operations() {
+        let mut dev = IS25xP::try_new(MockQspi {
+            erase_polls: 3,
+            ..MockQspi::new()
+        })
+        .unwrap();
+
+        let sector = dev.memory_map().sector_at(0).unwrap();
+        dev.start_erase_sector(&sector).unwrap();
+        assert!(matches!(dev.start_erase_chip(), Err(Error::Busy)));
+        assert!(matches!(dev.poll(), Err(nb::Error::WouldBlock)));
+        assert!(matches!(dev.poll(), Err(nb::Error::WouldBlock)));
+        assert!(matches!(dev.poll(), Ok(())));
+
+        dev.start_write_page(0x100, &[0x00; 4]).unwrap();
+        assert!(matches!(dev.poll(), Ok(())));
+
+        dev.qspi.locked = true;
+        dev.start_write_page(0x100, &[0x00; 4]).unwrap();
+        assert!(matches!(
+            dev.poll(),
+            Err(nb::Error::Other(Error::WriteProtected))
+        ));
+    }
+
+    #[test]
+    fn reject_page_writes_past_the_address_space() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        let configured = dev.qspi.write_operations.borrow().len();
+
+        assert!(matches!(
+            dev.start_write_page(u32::MAX - 1, &[0x00; 4]),
+            Err(Error::OutOfBounds)
+        ));
+        assert_eq!(dev.qspi.write_operations.borrow().len(), configured);
+    }
+
+    #[test]
+    fn reject_native_reads_past_the_address_space() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+
+        let mut buf = [0u8; 4];
+        assert!(matches!(
+            dev.read_native(u32::MAX - 1, &mut buf),
+            Err(Error::OutOfBounds)
+        ));
+    }
+
+    /// Accumulates the requested delays
+    #[derive(Default)]
+    struct MockDelay(u64);
+
+    impl embedded_hal::delay::DelayNs for MockDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0 += ns as u64;
+        }
+    }
+
+    #[test]
+    fn time_out_stuck_operations() {
+        let mut dev = IS25xP::try_new(MockQspi::new())
+            .unwrap()
+            .with_delay(MockDelay::default());
+
+        dev.qspi.erase_polls = 2;
+        let sector = dev.memory_map().sector_at(0).unwrap();
+        dev.erase_sector(&sector).unwrap();
+        assert_eq!(dev.delay.as_ref().unwrap().0, 20_000);
+
+        dev.qspi.erase_polls = u32::MAX;
+        assert!(matches!(dev.erase_sector(&sector), Err(Error::Timeout)));
+        let elapsed = dev.delay.as_ref().unwrap().0 - 20_000;
+        assert_eq!(elapsed, dev.params().timeouts().sector_erase as u64 * 1000);
+
+        // Without a delay source the driver keeps waiting
+        dev.qspi.busy = 5;
+        dev.qspi.erase_polls = 2;
+        let mut dev = IS25xP::try_new_with_params(dev.qspi, IS25xPParams::default()).unwrap();
+        dev.erase_sector(&sector).unwrap();
+    }
+
+    #[test]
+    fn keep_timeout_of_suspended_erase() {
+        let mut dev = IS25xP::try_new(MockQspi {
+            erase_polls: 1000,
+            ..MockQspi::new()
+        })
+        .unwrap()
+        .with_delay(MockDelay::default());
+
+        let block = dev.memory_map().block_at(0).unwrap();
+        dev.start_erase_block(&block).unwrap();
+        dev.suspend().unwrap();
+        dev.write(BLOCK_SIZE, &[0x00; 4]).unwrap();
+
+        // The resumed erase outlasts the program issued during the suspend
+        dev.resume().unwrap();
+        dev.wait().unwrap();
+        assert!(dev.delay.as_ref().unwrap().0 > dev.params().timeouts().page_program as u64 * 1000);
+    }
+
+    #[test]
+    fn verify_programs_and_erases() {
+        let qspi = MockQspi::with_memory([0x9D, 0x60, 0x15], 2 * 1024 * 1024);
+        let mut dev = IS25xP::try_new(qspi).unwrap();
+        assert!(!dev.verify());
+        dev.set_verify(true);
+
+        let data: Vec<u8> = (0..=255).collect();
+        dev.write_page(0x1000, &data).unwrap();
+
+        // Programming can only clear bits, so a second program of the same
+        // page reads back the combination of both
+        assert!(matches!(
+            dev.write_page(0x1000, &[0x55; 4]),
+            Err(Error::VerifyFailed { address: 0x1000 })
+        ));
+
+        let sector = dev.memory_map().sector_at(0x1000).unwrap();
+        dev.erase_sector(&sector).unwrap();
+        assert!(dev.qspi.memory.as_ref().unwrap()[0x1000..0x2000]
+            .iter()
+            .all(|&b| b == 0xFF));
+
+        dev.qspi.worn = Some(0x10123);
+        let block = dev.memory_map().block_at(0x10000).unwrap();
+        assert!(matches!(
+            dev.erase_block(&block),
+            Err(Error::VerifyFailed { address: 0x10123 })
+        ));
+
+        dev.set_verify(false);
+        dev.erase_block(&block).unwrap();
+    }
+
+    #[test]
+    fn find_blank_regions() {
+        let qspi = MockQspi::with_memory([0x9D, 0x60, 0x15], 2 * 1024 * 1024);
+        let mut dev = IS25xP::try_new(qspi).unwrap();
+        let end = dev.memory_map().end();
+
+        assert!(dev.is_blank(0..end).unwrap());
+        dev.write_page(0x3080, &[0xFF, 0xFF, 0x7F]).unwrap();
+        dev.write_page(0x5000, &[0x00]).unwrap();
+
+        assert_eq!(dev.first_non_blank(0..end).unwrap(), Some(0x3082));
+        assert_eq!(dev.first_non_blank(0x3083..end).unwrap(), Some(0x5000));
+        assert!(dev.is_blank(0x3000..0x3082).unwrap());
+        assert!(!dev.is_blank(0x3000..0x4000).unwrap());
+        assert!(matches!(
+            dev.first_non_blank(0..end + 1),
+            Err(Error::OutOfBounds)
+        ));
+
+        dev.qspi.write_operations.borrow_mut().clear();
+        assert_eq!(dev.erase_non_blank(0, 0x10000).unwrap(), 2);
+        assert!(dev.is_blank(0..end).unwrap());
+
+        let erases: Vec<_> = dev
+            .qspi
+            .write_operations
+            .borrow()
+            .iter()
+            .filter(|(i, _, _)| *i == ERASE_SECTOR.instruction)
+            .map(|(_, address, _)| address.unwrap())
+            .collect();
+        assert_eq!(erases, [0x5000, 0x3000]);
+
+        assert!(matches!(
+            dev.erase_non_blank(0x100, 0x1000),
+            Err(Error::Alignment)
+        ));
+    }
+
+    /// Checksum recording the size of every chunk it is fed
+    #[derive(Default)]
+    struct ChunkSizes(Vec<usize>);
+
+    impl Checksum for ChunkSizes {
+        type Output = Vec<usize>;
+
+        fn update(&mut self, data: &[u8]) {
+            self.0.push(data.len());
+        }
+
+        fn finish(&self) -> Vec<usize> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn compute_checksums() {
+        let qspi = MockQspi::with_memory([0x9D, 0x60, 0x15], 2 * 1024 * 1024);
+        let mut dev = IS25xP::try_new(qspi).unwrap();
+
+        let data: Vec<u8> = (0..=255).collect();
+        dev.write_page(0x100, &data).unwrap();
+        dev.write_page(0x300, &data[..0x10]).unwrap();
+
+        let range = 0x80..0x1000;
+        let image = &dev.qspi.memory.as_ref().unwrap()[0x80..0x1000];
+        let mut crc32 = Crc32::new();
+        crc32.update(image);
+        let mut crc16 = Crc16::new();
+        crc16.update(image);
+        let (crc32, crc16) = (crc32.finish(), crc16.finish());
+
+        assert_eq!(dev.checksum(range.clone(), Crc32::new()).unwrap(), crc32);
+        assert_eq!(dev.checksum(range.clone(), Crc16::new()).unwrap(), crc16);
+        assert_eq!(
+            dev.checksum(range, ChunkSizes::default()).unwrap(),
+            [512, 512, 512, 512, 512, 512, 512, 384]
+        );
+
+        let end = dev.memory_map().end();
+        assert!(matches!(
+            dev.checksum(0..end + 1, Crc32::new()),
+            Err(Error::OutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn select_read_modes() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        assert_eq!(dev.read_mode(), None);
+
+        let modes = [
+            (ReadMode::Normal, 0x03, 0, 0x02),
+            (ReadMode::Fast, 0x0B, 8, 0x02),
+            (ReadMode::DualOutput, 0x3B, 8, 0x02),
+            (ReadMode::DualIo, 0xBB, 4, 0x02),
+            (ReadMode::QuadOutput, 0x6B, 8, 0x32),
+            (ReadMode::QuadIo, 0xEB, 6, 0x32),
+            (ReadMode::FastDtr, 0x0D, 8, 0x02),
+            (ReadMode::DualIoDtr, 0xBD, 4, 0x02),
+            (ReadMode::QuadIoDtr, 0xED, 6, 0x32),
+        ];
+        for (mode, read, dummy_cycles, program) in modes {
+            dev.set_read_mode(mode).unwrap();
+            assert_eq!(dev.read_mode(), Some(mode));
+
+            let mut buf = [0u8; 4];
+            dev.read(0x100, &mut buf).unwrap();
+            assert_eq!(dev.qspi.reads.last(), Some(&(read, dummy_cycles)));
+
+            dev.write(0x100, &buf).unwrap();
+            let operations = dev.qspi.write_operations.borrow();
+            assert_eq!(operations[0].0.unwrap().0, program);
+        }
+
+        let mut dev = spi_device(IS25LP128);
+        assert!(matches!(
+            dev.set_read_mode(ReadMode::DualOutput),
+            Err(Error::Unsupported)
+        ));
+        assert!(matches!(
+            dev.set_read_mode(ReadMode::FastDtr),
+            Err(Error::Unsupported)
+        ));
+        dev.set_read_mode(ReadMode::Normal).unwrap();
+
+        let mut buf = [0xFFu8; 4];
+        dev.read(0x010203, &mut buf).unwrap();
+        let spi = dev.qspi.release();
+        assert_eq!(spi.commands(), [&[0x03, 0x01, 0x02, 0x03][..]]);
+    }
+
+    #[test]
+    fn enter_and_exit_qpi() {
+        let qspi = MockQspi::with_memory([0x9D, 0x60, 0x15], 2 * 1024 * 1024);
+        let mut dev = IS25xP::try_new(qspi).unwrap();
+        assert!(!dev.qpi());
+
+        dev.enter_qpi().unwrap();
+        assert!(dev.qpi() && dev.qspi.qpi);
+        assert_eq!(
+            dev.read_jedec_id().unwrap(),
+            JedecId::from([0x9D, 0x60, 0x15])
+        );
+
+        dev.write(0x100, &[0x12, 0x34]).unwrap();
+        let mut buf = [0u8; 2];
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+        assert_eq!(
+            dev.qspi.write_operations.borrow()[0].0,
+            Some((0x02, QspiMode::QuadChannel))
+        );
+
+        // Modes without a QPI counterpart are refused
+        assert!(matches!(
+            dev.set_read_mode(ReadMode::QuadOutput),
+            Err(Error::Unsupported)
+        ));
+        dev.set_read_mode(ReadMode::Fast).unwrap();
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0x0B, 6)));
+        dev.set_read_mode(ReadMode::FastDtr).unwrap();
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0x0D, 6)));
+        dev.set_read_mode(ReadMode::Fast).unwrap();
+
+        dev.exit_qpi().unwrap();
+        assert!(!dev.qpi() && !dev.qspi.qpi);
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+        assert_eq!(dev.qspi.reads.last(), Some(&(0x0B, 8)));
+
+        dev.set_read_mode(ReadMode::QuadOutput).unwrap();
+        assert!(matches!(dev.enter_qpi(), Err(Error::Unsupported)));
+
+        // Devices reading with the 4 byte address opcodes
+        let mut dev = IS25xP::try_new(MockQspi::with_id([0x9D, 0x60, 0x19])).unwrap();
+        dev.enter_qpi().unwrap();
+
+        let mut dev = spi_device(IS25LP128);
+        assert!(matches!(dev.enter_qpi(), Err(Error::Unsupported)));
+    }
+
+    #[test]
+    fn recover_from_qpi() {
+        let dev = IS25xP::try_new(MockQspi {
+            qpi: true,
+            ..MockQspi::new()
+        })
+        .unwrap();
+
+        assert!(!dev.qpi() && !dev.qspi.qpi);
+        assert_eq!(dev.jedec_id(), JedecId::from(IS25LP128));
+        assert_eq!(
+            dev.qspi.write_operations.borrow().back().unwrap().0,
+            Some((0xF5, QspiMode::QuadChannel))
+        );
+    }
+
+    #[test]
+    fn configure_read_parameters() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        assert_eq!(dev.read_parameters().unwrap(), ReadParameters::new());
+
+        let mut buf = [0u8; 4];
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0xEB, 6)));
+
+        let params = ReadParameters::new()
+            .with_dummy_cycles(10)
+            .unwrap()
+            .with_wrap(Some(WrapLength::Bytes64));
+        dev.set_read_parameters(params).unwrap();
+        assert_eq!(dev.qspi.read_parameters, 0b01010111);
+
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0xEB, 10)));
+        dev.set_read_mode(ReadMode::Normal).unwrap();
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0x03, 0)));
+
+        // Parameters left by a previous session are picked up
+        let mut dev = IS25xP::try_new(MockQspi {
+            read_parameters: 0b01000000,
+            ..MockQspi::new()
+        })
+        .unwrap();
+        dev.read(0x100, &mut buf).unwrap();
+        assert_eq!(dev.qspi.reads.last(), Some(&(0xEB, 8)));
+
+        assert_eq!(
+            dev.drive_strength().unwrap(),
+            Some(DriveStrength::Percent50)
+        );
+        dev.qspi.extended_read |= 0x01;
+        dev.set_drive_strength(DriveStrength::Percent100).unwrap();
+        assert_eq!(dev.qspi.extended_read, 0b11000001);
+        assert_eq!(
+            dev.drive_strength().unwrap(),
+            Some(DriveStrength::Percent100)
+        );
+    }
+
+    #[test]
+    fn report_error_flags() {
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        dev.write_page(0x100, &[0x55; 4]).unwrap();
+
+        dev.qspi.fault = 0x04;
+        assert!(matches!(
+            dev.write_page(0x100, &[0x55; 4]),
+            Err(Error::ProgramFailed)
+        ));
+        assert_eq!(dev.qspi.write_operations.borrow()[0].0.unwrap().0, 0x82);
+        assert!(!dev.extended_read_parameters().unwrap().p_err());
+
+        dev.qspi.fault = 0x08;
+        let sector = dev.memory_map().sector_at(0x1000).unwrap();
+        assert!(matches!(dev.erase_sector(&sector), Err(Error::EraseFailed)));
+
+        dev.qspi.fault = 0x0A;
+        assert!(matches!(dev.erase_chip(), Err(Error::ProtectionError)));
+
+        dev.start_erase_sector(&sector).unwrap();
+        assert!(matches!(
+            dev.poll(),
+            Err(nb::Error::Other(Error::ProtectionError))
+        ));
+
+        dev.qspi.fault = 0x04;
+        assert!(matches!(
+            dev.write_otp(0, 0, &[0x55; 4]),
+            Err(Error::ProgramFailed)
+        ));
+        dev.qspi.fault = 0x08;
+        assert!(matches!(dev.erase_otp(0), Err(Error::EraseFailed)));
+
+        // Flags are cleared once reported
+        dev.qspi.fault = 0;
+        dev.erase_sector(&sector).unwrap();
+        assert_eq!(dev.qspi.extended_read, 0xE0);
+    }
+
     #[test]
     fn write_one_aligned_partial_block() {
         let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
@@ -60,6 +1279,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (QUAD_WRITE.instruction, Some(0x100), Some(bytes.len())),
@@ -90,6 +1310,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (QUAD_WRITE.instruction, Some(0x100), Some(bytes.len())),
@@ -119,6 +1340,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (QUAD_WRITE.instruction, Some(0x100), Some(256)),
@@ -154,6 +1376,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (QUAD_WRITE.instruction, Some(0x110), Some(240)),
@@ -185,6 +1408,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (QUAD_WRITE.instruction, Some(0x110), Some(240)),
@@ -221,6 +1445,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_SECTOR.instruction, Some(0x00), None),
@@ -240,6 +1465,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_SECTOR.instruction, Some(0x00), None),
@@ -265,6 +1491,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_HALF_BLOCK.instruction, Some(0x00), None),
@@ -285,6 +1512,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_HALF_BLOCK.instruction, Some(start), None),
@@ -310,6 +1538,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_BLOCK.instruction, Some(0x00), None),
@@ -329,6 +1558,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_BLOCK.instruction, Some(0x00), None),
@@ -354,6 +1584,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_HALF_BLOCK.instruction, Some(start), None),
@@ -383,6 +1614,7 @@ mod it_should {
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_SECTOR.instruction, Some(start), None),
@@ -410,10 +1642,11 @@ mod it_should {
     fn erase_chip() {
         let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
 
-        dev.erase(0x00, MEMORY_SIZE).unwrap();
+        dev.erase(0x00, Density::Mbit128.bytes()).unwrap();
 
         let operations = dev.qspi.write_operations.borrow();
         let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
             (WRITE_STATUS.instruction, None, Some(1)),
             (WRITE_ENABLE.instruction, None, None),
             (ERASE_CHIP.instruction, None, None),
@@ -424,4 +1657,438 @@ mod it_should {
             assert_eq!(op, &expected_operations[i]);
         }
     }
+
+    /// Records the bytes written in each SPI transaction
+    struct MockSpi {
+        transactions: Vec<Vec<u8>>,
+        jedec_id: [u8; 3],
+    }
+
+    impl MockSpi {
+        /// Transactions other than the register and ID reads
+        fn commands(&self) -> Vec<&[u8]> {
+            self.transactions
+                .iter()
+                .filter(|t| ![0x05, 0x48, 0x61, 0x81, 0x9F].contains(&t[0]))
+                .map(|t| t.as_slice())
+                .collect()
+        }
+    }
+
+    impl embedded_hal::spi::ErrorType for MockSpi {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_hal::spi::SpiDevice for MockSpi {
+        fn transaction(
+            &mut self,
+            operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            let mut written = Vec::new();
+            for op in operations {
+                match op {
+                    embedded_hal::spi::Operation::Write(data) => written.extend_from_slice(data),
+                    embedded_hal::spi::Operation::Read(buf) if written == [0x9F] => {
+                        buf.copy_from_slice(&self.jedec_id)
+                    }
+                    embedded_hal::spi::Operation::Read(buf) if written[0] == 0x4B => {
+                        buf.copy_from_slice(&UNIQUE_ID)
+                    }
+                    // Make sure we do not get stuck in `wait_busy` state
+                    embedded_hal::spi::Operation::Read(buf) => buf.fill(0),
+                    _ => unimplemented!(),
+                }
+            }
+            self.transactions.push(written);
+            Ok(())
+        }
+    }
+
+    fn spi_device(jedec_id: [u8; 3]) -> IS25xP<spi::SpiTransport<MockSpi>> {
+        IS25xP::try_new(spi::SpiTransport::new(MockSpi {
+            transactions: Vec::new(),
+            jedec_id,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn write_over_spi() {
+        let mut dev = spi_device(IS25LP128);
+
+        dev.write(0x123456, &[0xAA, 0xBB]).unwrap();
+
+        let spi = dev.qspi.release();
+        assert_eq!(
+            spi.commands(),
+            [&[0x06][..], &[0x02, 0x12, 0x34, 0x56, 0xAA, 0xBB]]
+        );
+    }
+
+    #[test]
+    fn read_over_spi() {
+        let mut dev = spi_device(IS25LP128);
+
+        let mut buf = [0xFFu8; 4];
+        dev.read(0x010203, &mut buf).unwrap();
+
+        let spi = dev.qspi.release();
+        assert_eq!(spi.commands(), [&[0x0B, 0x01, 0x02, 0x03, 0x00][..]]);
+        assert_eq!(buf, [0u8; 4]);
+    }
+
+    #[test]
+    fn read_unique_id_over_spi() {
+        let mut dev = spi_device(IS25LP128);
+
+        assert_eq!(dev.unique_id().unwrap(), UNIQUE_ID);
+
+        let spi = dev.qspi.release();
+        assert_eq!(spi.commands(), [&[0x4B, 0x00, 0x00, 0x00, 0x00][..]]);
+    }
+
+    #[test]
+    fn erase_over_spi() {
+        let mut dev = spi_device(IS25LP128);
+
+        dev.erase(SECTOR_SIZE, SECTOR_SIZE * 2).unwrap();
+
+        let spi = dev.qspi.release();
+        assert_eq!(spi.commands(), [&[0x06][..], &[0xD7, 0x00, 0x10, 0x00]]);
+    }
+
+    #[test]
+    fn use_four_byte_opcodes() {
+        let mut dev = spi_device([0x9D, 0x60, 0x19]);
+        assert_eq!(dev.address_mode(), AddressMode::FourByteOpcodes);
+
+        let mut buf = [0u8; 1];
+        dev.read(0x0100_0000, &mut buf).unwrap();
+        dev.write(0x01FF_FFFF, &[0xAA]).unwrap();
+        dev.erase(0x0100_0000, 0x0100_0000 + SECTOR_SIZE).unwrap();
+        dev.erase(0x0100_0000, 0x0100_0000 + HALFBLOCK_SIZE)
+            .unwrap();
+        dev.erase(0x0100_0000, 0x0100_0000 + BLOCK_SIZE).unwrap();
+
+        let spi = dev.qspi.release();
+        assert_eq!(
+            spi.commands(),
+            [
+                &[0x0C, 0x01, 0x00, 0x00, 0x00, 0x00][..],
+                &[0x06],
+                &[0x12, 0x01, 0xFF, 0xFF, 0xFF, 0xAA],
+                &[0x06],
+                &[0x21, 0x01, 0x00, 0x00, 0x00],
+                &[0x06],
+                &[0x5C, 0x01, 0x00, 0x00, 0x00],
+                &[0x06],
+                &[0xDC, 0x01, 0x00, 0x00, 0x00],
+            ]
+        );
+    }
+
+    /// 32 MiB device without 4 byte erase opcodes
+    struct NoFourByteEraseParams;
+
+    impl FlashParams for NoFourByteEraseParams {
+        fn page_size(&self) -> u32 {
+            256
+        }
+        fn sector_size(&self) -> u32 {
+            SECTOR_SIZE
+        }
+        fn halfblock_size(&self) -> u32 {
+            HALFBLOCK_SIZE
+        }
+        fn block_size(&self) -> u32 {
+            BLOCK_SIZE
+        }
+        fn capacity(&self) -> u32 {
+            32 * 1024 * 1024
+        }
+        fn sector_erase_command(&self) -> QspiWriteCommand<'static> {
+            ERASE_SECTOR
+        }
+        fn halfblock_erase_command(&self) -> QspiWriteCommand<'static> {
+            commands::ERASE_HALF_BLOCK
+        }
+        fn block_erase_command(&self) -> QspiWriteCommand<'static> {
+            ERASE_BLOCK
+        }
+        fn read_command(&self, _lanes: QspiMode) -> QspiReadCommand {
+            commands::READ
+        }
+        fn program_command(&self, _lanes: QspiMode) -> QspiWriteCommand<'static> {
+            commands::WRITE
+        }
+        fn four_byte_opcode(&self, opcode: u8) -> Option<u8> {
+            match opcode {
+                0x20 | 0xD7 | 0x52 | 0xD8 => None,
+                _ => commands::four_byte_opcode(opcode),
+            }
+        }
+    }
+
+    #[test]
+    fn never_mix_opcode_and_address_width() {
+        // Only some commands have 4 byte opcodes, so 4 byte address mode is
+        // used for all of them
+        let dev = IS25xP::try_new_with_params(MockQspi::new(), NoFourByteEraseParams).unwrap();
+        assert_eq!(dev.address_mode(), AddressMode::FourByteMode);
+
+        // A command without a 4 byte opcode is never sent with a 4 byte
+        // address
+        let params = NoFourByteEraseParams;
+        assert!(AddressMode::FourByteOpcodes
+            .write_at(&params, ERASE_SECTOR, 0x0100_0000)
+            .is_none());
+        assert!(AddressMode::FourByteOpcodes
+            .read_at(&params, commands::READ, 0x0100_0000)
+            .is_some());
+    }
+
+    #[cfg(feature = "async")]
+    impl AsyncQspi for MockQspi {
+        type Error = ();
+
+        async fn write(&mut self, cmd: QspiWriteCommand<'_>) -> Result<(), Self::Error> {
+            Qspi::write(self, cmd)
+        }
+
+        async fn transfer(
+            &mut self,
+            cmd: QspiReadCommand,
+            buf: &mut [u8],
+        ) -> Result<(), Self::Error> {
+            Qspi::transfer(self, cmd, buf)
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl embedded_hal_async::spi::SpiDevice for MockSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiDevice::transaction(self, operations)
+        }
+    }
+
+    /// Drive `future` to completion, counting the times it yielded
+    #[cfg(feature = "async")]
+    fn block_on<F: core::future::Future>(future: F) -> (F::Output, usize) {
+        let mut future = core::pin::pin!(future);
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        let mut yields = 0;
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                core::task::Poll::Ready(output) => return (output, yields),
+                core::task::Poll::Pending => yields += 1,
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn write_and_erase_async() {
+        use embedded_storage_async::nor_flash::NorFlash;
+
+        let (dev, _) = block_on(IS25xPAsync::try_new(MockQspi {
+            erase_polls: 5,
+            ..MockQspi::new()
+        }));
+        let mut dev = dev.unwrap();
+
+        let bytes = [0u8; 300];
+        let (result, _) = block_on(dev.write(0x80, &bytes));
+        result.unwrap();
+        let (result, yields) = block_on(dev.erase(0, SECTOR_SIZE));
+        result.unwrap();
+        assert_eq!(yields, 5);
+
+        let qspi = dev.release();
+        let operations = qspi.write_operations.borrow();
+        let expected_operations = [
+            (WRITE_ENABLE.instruction, None, None),
+            (WRITE_STATUS.instruction, None, Some(1)),
+            (WRITE_ENABLE.instruction, None, None),
+            (QUAD_WRITE.instruction, Some(0x80), Some(0x80)),
+            (WRITE_ENABLE.instruction, None, None),
+            (QUAD_WRITE.instruction, Some(0x100), Some(0xAC)),
+            (WRITE_ENABLE.instruction, None, None),
+            (ERASE_SECTOR.instruction, Some(0), None),
+        ];
+
+        assert_eq!(operations.len(), expected_operations.len());
+        for (i, op) in operations.iter().rev().enumerate() {
+            assert_eq!(op, &expected_operations[i]);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn refuse_protected_writes_async() {
+        use embedded_storage_async::nor_flash::NorFlash;
+
+        let mut dev = IS25xP::try_new(MockQspi::new()).unwrap();
+        dev.set_block_protection(BlockProtection::covering(1))
+            .unwrap();
+        let (dev, _) = block_on(IS25xPAsync::try_new(dev.qspi));
+        let mut dev = dev.unwrap();
+        let end = dev.memory_map().end();
+
+        // Nothing is written when any part of the range is protected
+        let (result, _) = block_on(dev.write(end - BLOCK_SIZE - 4, &[0x00; 8]));
+        assert!(matches!(result, Err(Error::WriteProtected)));
+        let (result, _) = block_on(dev.erase(
+            end - BLOCK_SIZE - SECTOR_SIZE,
+            end - BLOCK_SIZE + SECTOR_SIZE,
+        ));
+        assert!(matches!(result, Err(Error::WriteProtected)));
+
+        let qspi = dev.release();
+        assert!(!qspi
+            .write_operations
+            .borrow()
+            .iter()
+            .any(|op| op.0 == QUAD_WRITE.instruction || op.0 == ERASE_SECTOR.instruction));
+    }
+
+    #[cfg(feature = "async")]
+    impl embedded_hal_async::delay::DelayNs for MockDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.0 += ns as u64;
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn time_out_stuck_operations_async() {
+        let mut delay = MockDelay::default();
+        let qspi = MockQspi {
+            erase_polls: u32::MAX,
+            ..MockQspi::new()
+        };
+        let (dev, _) = block_on(IS25xPAsync::try_new_with_delay(qspi, &mut delay));
+        let mut dev = dev.unwrap();
+
+        let sector = dev.memory_map().sector_at(0).unwrap();
+        let (result, _) = block_on(dev.erase_sector(&sector));
+        assert!(matches!(result, Err(Error::Timeout)));
+        let timeout = dev.params().timeouts().sector_erase as u64;
+        let mut qspi = dev.release();
+        assert_eq!(delay.0, timeout * 1000);
+
+        // Yielding does not measure time, so the driver keeps waiting
+        qspi.busy = 5;
+        let (dev, _) = block_on(IS25xPAsync::try_new(qspi));
+        let mut qspi = dev.unwrap().release();
+
+        // Also when initialized from params, even past the maximum duration
+        // counted in status reads
+        qspi.erase_polls = timeout as u32 / 10 + 10;
+        let (dev, _) = block_on(IS25xPAsync::try_new_with_params(
+            qspi,
+            IS25xPParams::default(),
+        ));
+        let mut dev = dev.unwrap();
+        let (result, _) = block_on(dev.erase_sector(&sector));
+        result.unwrap();
+
+        // Until a delay source is given
+        let mut dev = dev.with_delay(&mut delay);
+        let (result, _) = block_on(dev.erase_sector(&sector));
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn verify_programs_and_erases_async() {
+        let qspi = MockQspi::with_memory([0x9D, 0x60, 0x15], 2 * 1024 * 1024);
+        let (dev, _) = block_on(IS25xPAsync::try_new(qspi));
+        let mut dev = dev.unwrap();
+        assert!(!dev.verify());
+        dev.set_verify(true);
+
+        let data: Vec<u8> = (0..=255).collect();
+        block_on(dev.write_page(0x1000, &data)).0.unwrap();
+        assert!(matches!(
+            block_on(dev.write_page(0x1000, &[0x55; 4])).0,
+            Err(Error::VerifyFailed { address: 0x1000 })
+        ));
+
+        let sector = dev.memory_map().sector_at(0x1000).unwrap();
+        block_on(dev.erase_sector(&sector)).0.unwrap();
+
+        let mut qspi = dev.release();
+        qspi.worn = Some(0x10123);
+        let (dev, _) = block_on(IS25xPAsync::try_new(qspi));
+        let mut dev = dev.unwrap();
+        dev.set_verify(true);
+        let block = dev.memory_map().block_at(0x10000).unwrap();
+        assert!(matches!(
+            block_on(dev.erase_block(&block)).0,
+            Err(Error::VerifyFailed { address: 0x10123 })
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn report_error_flags_async() {
+        let (dev, _) = block_on(IS25xPAsync::try_new(MockQspi {
+            fault: 0x04,
+            ..MockQspi::new()
+        }));
+        let mut dev = dev.unwrap();
+
+        assert!(matches!(
+            block_on(dev.write_page(0x100, &[0x55; 4])).0,
+            Err(Error::ProgramFailed)
+        ));
+        let (erp, _) = block_on(dev.extended_read_parameters());
+        assert!(!erp.unwrap().p_err());
+
+        let mut qspi = dev.release();
+        qspi.fault = 0x08;
+        let (dev, _) = block_on(IS25xPAsync::try_new(qspi));
+        let mut dev = dev.unwrap();
+        let sector = dev.memory_map().sector_at(0).unwrap();
+        assert!(matches!(
+            block_on(dev.erase_sector(&sector)).0,
+            Err(Error::EraseFailed)
+        ));
+
+        let mut qspi = dev.release();
+        qspi.fault = 0x02;
+        let (dev, _) = block_on(IS25xPAsync::try_new(qspi));
+        let mut dev = dev.unwrap();
+        assert!(matches!(
+            block_on(dev.erase_chip()).0,
+            Err(Error::ProtectionError)
+        ));
+
+        let qspi = dev.release();
+        assert_eq!(qspi.extended_read, 0xE0);
+        assert_eq!(qspi.write_operations.borrow()[0].0.unwrap().0, 0x82);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn read_over_spi_async() {
+        use embedded_storage_async::nor_flash::ReadNorFlash;
+
+        let (dev, _) = block_on(IS25xPAsync::try_new(spi::SpiTransport::new(MockSpi {
+            transactions: Vec::new(),
+            jedec_id: IS25LP128,
+        })));
+        let mut dev = dev.unwrap();
+
+        let mut buf = [0xFFu8; 4];
+        let (result, _) = block_on(dev.read(0x010203, &mut buf));
+        result.unwrap();
+
+        let spi = dev.release().release();
+        assert_eq!(spi.commands(), [&[0x0B, 0x01, 0x02, 0x03, 0x00][..]]);
+        assert_eq!(buf, [0u8; 4]);
+    }
 }